@@ -0,0 +1,236 @@
+use std::time::{Duration, Instant};
+
+use my_service_bus_abstractions::queue_with_intervals::{QueueIndexRange, QueueWithIntervals};
+
+const INTERVALS_AMOUNT: i64 = 100_000;
+const OPERATIONS_AMOUNT: i64 = 10_000;
+
+fn create_fragmented_queue() -> QueueWithIntervals {
+    let mut queue = QueueWithIntervals::new();
+
+    for i in 0..INTERVALS_AMOUNT {
+        queue.enqueue_range(&QueueIndexRange::restore(i * 10, i * 10 + 4));
+    }
+
+    queue
+}
+
+#[derive(Clone)]
+struct LinearScanQueue {
+    intervals: Vec<QueueIndexRange>,
+}
+
+impl LinearScanQueue {
+    fn new(queue: &QueueWithIntervals) -> Self {
+        Self {
+            intervals: queue.get_intervals().to_vec(),
+        }
+    }
+
+    fn enqueue(&mut self, id: i64) {
+        self.enqueue_range(&QueueIndexRange::restore(id, id));
+    }
+
+    fn enqueue_range(&mut self, range: &QueueIndexRange) {
+        let mut from_id = range.from_id;
+        let mut to_id = range.to_id;
+        let mut joined = None;
+
+        for (index, item) in self.intervals.iter().enumerate() {
+            if item.to_id + 1 >= range.from_id && item.from_id <= range.to_id + 1 {
+                let (first_index, _) = joined.unwrap_or((index, index));
+                joined = Some((first_index, index));
+                from_id = from_id.min(item.from_id);
+                to_id = to_id.max(item.to_id);
+            }
+        }
+
+        match joined {
+            Some((first_index, last_index)) => {
+                self.intervals.splice(
+                    first_index..=last_index,
+                    [QueueIndexRange::restore(from_id, to_id)],
+                );
+            }
+            None => {
+                let index = self
+                    .intervals
+                    .iter()
+                    .position(|item| item.from_id > range.to_id)
+                    .unwrap_or(self.intervals.len());
+
+                self.intervals.insert(index, range.clone());
+            }
+        }
+    }
+
+    fn remove(&mut self, id: i64) -> bool {
+        for index in 0..self.intervals.len() {
+            let item = &mut self.intervals[index];
+
+            if id < item.from_id || item.to_id < id {
+                continue;
+            }
+
+            if item.from_id == item.to_id {
+                self.intervals.remove(index);
+            } else if item.from_id == id {
+                item.from_id += 1;
+            } else if item.to_id == id {
+                item.to_id -= 1;
+            } else {
+                let new_item = QueueIndexRange::restore(id + 1, item.to_id);
+                item.to_id = id - 1;
+                self.intervals.insert(index + 1, new_item);
+            }
+
+            return true;
+        }
+
+        false
+    }
+}
+
+fn get_intervals(intervals: &[QueueIndexRange]) -> Vec<(i64, i64)> {
+    intervals
+        .iter()
+        .map(|item| (item.from_id, item.to_id))
+        .collect()
+}
+
+fn get_id(i: i64) -> i64 {
+    (i * 7919 % INTERVALS_AMOUNT) * 10
+}
+
+fn print_result(name: &str, duration: Duration) {
+    println!(
+        "{:<40} {:>10} ns/op",
+        name,
+        duration.as_nanos() / OPERATIONS_AMOUNT as u128
+    );
+}
+
+fn bench_has_message(queue: &QueueWithIntervals) {
+    let started = Instant::now();
+    let mut found = 0;
+    for i in 0..OPERATIONS_AMOUNT {
+        if queue.has_message(get_id(i) + 2) {
+            found += 1;
+        }
+    }
+    print_result("has_message", started.elapsed());
+
+    let started = Instant::now();
+    let mut found_linear = 0;
+    for i in 0..OPERATIONS_AMOUNT {
+        let id = get_id(i) + 2;
+        if queue
+            .get_intervals()
+            .iter()
+            .any(|item| item.from_id <= id && id <= item.to_id)
+        {
+            found_linear += 1;
+        }
+    }
+    print_result("has_message (linear scan)", started.elapsed());
+
+    assert_eq!(found, found_linear);
+}
+
+fn bench_len(queue: &QueueWithIntervals) {
+    let started = Instant::now();
+    let mut total = 0;
+    for _ in 0..OPERATIONS_AMOUNT {
+        total += queue.len();
+    }
+    print_result("len", started.elapsed());
+
+    let started = Instant::now();
+    let mut total_linear = 0;
+    for _ in 0..OPERATIONS_AMOUNT {
+        total_linear += queue
+            .get_intervals()
+            .iter()
+            .map(|item| item.len())
+            .sum::<i64>();
+    }
+    print_result("len (linear sum)", started.elapsed());
+
+    assert_eq!(total, total_linear);
+}
+
+fn bench_enqueue_and_remove(queue: &mut QueueWithIntervals, reference: &mut LinearScanQueue) {
+    let started = Instant::now();
+    for i in 0..OPERATIONS_AMOUNT {
+        queue.enqueue(get_id(i) + 7);
+    }
+    print_result("enqueue", started.elapsed());
+
+    let started = Instant::now();
+    for i in 0..OPERATIONS_AMOUNT {
+        reference.enqueue(get_id(i) + 7);
+    }
+    print_result("enqueue (linear scan)", started.elapsed());
+
+    let started = Instant::now();
+    for i in 0..OPERATIONS_AMOUNT {
+        queue.remove(get_id(i) + 7).unwrap();
+    }
+    print_result("remove", started.elapsed());
+
+    let started = Instant::now();
+    for i in 0..OPERATIONS_AMOUNT {
+        assert!(reference.remove(get_id(i) + 7));
+    }
+    print_result("remove (linear scan)", started.elapsed());
+
+    let started = Instant::now();
+    for i in 0..OPERATIONS_AMOUNT {
+        queue.remove(get_id(i) + 2).unwrap();
+    }
+    print_result("remove (split interval)", started.elapsed());
+
+    let started = Instant::now();
+    for i in 0..OPERATIONS_AMOUNT {
+        assert!(reference.remove(get_id(i) + 2));
+    }
+    print_result("remove (split interval, linear scan)", started.elapsed());
+
+    assert_eq!(
+        get_intervals(&reference.intervals),
+        get_intervals(queue.get_intervals())
+    );
+}
+
+fn bench_enqueue_range(queue: &mut QueueWithIntervals, reference: &mut LinearScanQueue) {
+    let started = Instant::now();
+    for i in 0..OPERATIONS_AMOUNT {
+        let from_id = get_id(i) + 5;
+        queue.enqueue_range(&QueueIndexRange::restore(from_id, from_id + 2));
+    }
+    print_result("enqueue_range", started.elapsed());
+
+    let started = Instant::now();
+    for i in 0..OPERATIONS_AMOUNT {
+        let from_id = get_id(i) + 5;
+        reference.enqueue_range(&QueueIndexRange::restore(from_id, from_id + 2));
+    }
+    print_result("enqueue_range (linear scan)", started.elapsed());
+
+    assert_eq!(
+        get_intervals(&reference.intervals),
+        get_intervals(queue.get_intervals())
+    );
+}
+
+fn main() {
+    let mut queue = create_fragmented_queue();
+    let mut reference = LinearScanQueue::new(&queue);
+
+    println!("Intervals: {}", queue.get_intervals().len());
+
+    bench_has_message(&queue);
+    bench_len(&queue);
+    bench_enqueue_and_remove(&mut queue, &mut reference);
+    bench_enqueue_range(&mut queue, &mut reference);
+}
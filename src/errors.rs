@@ -3,6 +3,8 @@ pub enum PublishError {
     NoConnectionToPublish,
     SerializationError(String),
     Disconnected,
+    QueueIsFull,
+    PublisherIsStopped,
     Other(String),
 }
 
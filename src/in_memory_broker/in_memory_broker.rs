@@ -0,0 +1,463 @@
+use std::{collections::HashMap, sync::Arc, sync::Mutex};
+
+use crate::{
+    publisher::MessageToPublish,
+    queue_with_intervals::{QueueIndexRange, QueueWithIntervals},
+    subscriber::TopicQueueType,
+    MyServiceBusPublisherClient, MyServiceBusSubscriberClient,
+    MyServiceBusSubscriberClientCallback, PublishError,
+};
+
+use super::{
+    InMemoryDeliveryToSend, InMemoryQueueData, InMemorySubscriberConnection, InMemoryTopicData,
+};
+
+pub const DEFAULT_MAX_MESSAGES_PER_DELIVERY: usize = 1000;
+
+pub struct InMemoryBrokerData {
+    pub topics: HashMap<String, InMemoryTopicData>,
+    pub next_connection_id: i32,
+    pub next_confirmation_id: i64,
+}
+
+pub struct MyServiceBusInMemoryBroker {
+    data: Mutex<InMemoryBrokerData>,
+    max_messages_per_delivery: usize,
+}
+
+impl MyServiceBusInMemoryBroker {
+    pub fn new() -> Self {
+        Self::new_with_max_messages_per_delivery(DEFAULT_MAX_MESSAGES_PER_DELIVERY)
+    }
+
+    pub fn new_with_max_messages_per_delivery(max_messages_per_delivery: usize) -> Self {
+        Self {
+            data: Mutex::new(InMemoryBrokerData {
+                topics: HashMap::new(),
+                next_connection_id: 0,
+                next_confirmation_id: 0,
+            }),
+            max_messages_per_delivery,
+        }
+    }
+
+    pub fn create_topic_if_not_exists(&self, topic_id: &str) {
+        let mut write_access = self.data.lock().unwrap();
+        if !write_access.topics.contains_key(topic_id) {
+            write_access.topics.insert(
+                topic_id.to_string(),
+                InMemoryTopicData::new(topic_id.to_string()),
+            );
+        }
+    }
+
+    pub fn subscribe(
+        &self,
+        callback: Arc<dyn MyServiceBusSubscriberClientCallback + Send + Sync + 'static>,
+    ) -> i32 {
+        let topic_id = callback.get_topic_id().to_string();
+        let queue_id = callback.get_queue_id().to_string();
+        let queue_type = callback.get_queue_type();
+
+        let connection_id = {
+            let mut write_access = self.data.lock().unwrap();
+
+            let connection_id = write_access.next_connection_id;
+            write_access.next_connection_id += 1;
+
+            let topic = write_access
+                .topics
+                .entry(topic_id.to_string())
+                .or_insert_with(|| InMemoryTopicData::new(topic_id.to_string()));
+
+            let queue = topic
+                .queues
+                .entry(queue_id.to_string())
+                .or_insert_with(|| InMemoryQueueData::new(queue_id.to_string(), queue_type));
+
+            if let TopicQueueType::PermanentWithSingleConnection = queue.queue_type {
+                let connections: Vec<i32> = queue
+                    .subscribers
+                    .iter()
+                    .map(|itm| itm.connection_id)
+                    .collect();
+
+                for connection_id in connections {
+                    queue.disconnect(connection_id);
+                }
+            }
+
+            queue.subscribers.push(InMemorySubscriberConnection {
+                connection_id,
+                callback,
+                confirmation_id: None,
+            });
+
+            connection_id
+        };
+
+        self.deliver(&topic_id, &queue_id);
+
+        connection_id
+    }
+
+    pub fn disconnect(&self, connection_id: i32) {
+        let mut queue_to_deliver = None;
+
+        {
+            let mut write_access = self.data.lock().unwrap();
+
+            for topic in write_access.topics.values_mut() {
+                let mut queue_to_delete = None;
+
+                for queue in topic.queues.values_mut() {
+                    if !queue.disconnect(connection_id) {
+                        continue;
+                    }
+
+                    if queue.subscribers.is_empty() {
+                        if let TopicQueueType::DeleteOnDisconnect = queue.queue_type {
+                            queue_to_delete = Some(queue.queue_id.to_string());
+                        }
+                    } else {
+                        queue_to_deliver =
+                            Some((topic.topic_id.to_string(), queue.queue_id.to_string()));
+                    }
+
+                    break;
+                }
+
+                if let Some(queue_id) = queue_to_delete {
+                    topic.queues.remove(&queue_id);
+                }
+            }
+        }
+
+        if let Some((topic_id, queue_id)) = queue_to_deliver {
+            self.deliver(&topic_id, &queue_id);
+        }
+    }
+
+    pub fn get_queue_size(&self, topic_id: &str, queue_id: &str) -> Option<usize> {
+        let read_access = self.data.lock().unwrap();
+        let queue = read_access.topics.get(topic_id)?.queues.get(queue_id)?;
+        Some(queue.messages.len())
+    }
+
+    fn confirm(
+        &self,
+        topic_id: &str,
+        queue_id: &str,
+        confirmation_id: i64,
+        connection_id: i32,
+        delivered: Option<&QueueWithIntervals>,
+    ) {
+        let confirmed = {
+            let mut write_access = self.data.lock().unwrap();
+
+            let queue = write_access
+                .topics
+                .get_mut(topic_id)
+                .and_then(|topic| topic.queues.get_mut(queue_id));
+
+            match queue {
+                Some(queue) => queue.confirm(confirmation_id, connection_id, delivered),
+                None => false,
+            }
+        };
+
+        if confirmed {
+            self.deliver(topic_id, queue_id);
+        }
+    }
+
+    fn deliver(&self, topic_id: &str, queue_id: &str) {
+        let mut to_send = Vec::new();
+
+        {
+            let mut write_access = self.data.lock().unwrap();
+            let write_access = &mut *write_access;
+
+            let queue = write_access
+                .topics
+                .get_mut(topic_id)
+                .and_then(|topic| topic.queues.get_mut(queue_id));
+
+            if let Some(queue) = queue {
+                while let Some(delivery) = queue.get_next_delivery(
+                    write_access.next_confirmation_id,
+                    self.max_messages_per_delivery,
+                ) {
+                    write_access.next_confirmation_id += 1;
+                    to_send.push(delivery);
+                }
+            }
+        }
+
+        for delivery in to_send {
+            send_delivery(delivery);
+        }
+    }
+}
+
+impl Default for MyServiceBusInMemoryBroker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn send_delivery(delivery: InMemoryDeliveryToSend) {
+    tokio::spawn(async move {
+        delivery
+            .callback
+            .new_events(
+                delivery.messages,
+                delivery.confirmation_id,
+                delivery.connection_id,
+            )
+            .await;
+    });
+}
+
+#[async_trait::async_trait]
+impl MyServiceBusPublisherClient for MyServiceBusInMemoryBroker {
+    async fn publish_message(
+        &self,
+        topic_id: &str,
+        message: MessageToPublish,
+        do_retry: bool,
+    ) -> Result<(), PublishError> {
+        self.publish_messages(topic_id, &[message], do_retry).await
+    }
+
+    async fn publish_messages(
+        &self,
+        topic_id: &str,
+        messages: &[MessageToPublish],
+        _do_retry: bool,
+    ) -> Result<(), PublishError> {
+        let queues: Vec<String> = {
+            let mut write_access = self.data.lock().unwrap();
+
+            let topic = write_access
+                .topics
+                .entry(topic_id.to_string())
+                .or_insert_with(|| InMemoryTopicData::new(topic_id.to_string()));
+
+            topic.publish(messages);
+
+            topic.queues.keys().cloned().collect()
+        };
+
+        for queue_id in queues {
+            self.deliver(topic_id, &queue_id);
+        }
+
+        Ok(())
+    }
+}
+
+impl MyServiceBusSubscriberClient for MyServiceBusInMemoryBroker {
+    fn confirm_delivery(
+        &self,
+        topic_id: &str,
+        queue_id: &str,
+        confirmation_id: i64,
+        connection_id: i32,
+        delivered: bool,
+    ) {
+        if delivered {
+            self.confirm(topic_id, queue_id, confirmation_id, connection_id, None);
+        } else {
+            self.confirm(
+                topic_id,
+                queue_id,
+                confirmation_id,
+                connection_id,
+                Some(&QueueWithIntervals::new()),
+            );
+        }
+    }
+
+    fn confirm_some_messages_ok(
+        &self,
+        topic_id: &str,
+        queue_id: &str,
+        confirmation_id: i64,
+        connection_id: i32,
+        ok_messages: Vec<QueueIndexRange>,
+    ) {
+        let mut delivered = QueueWithIntervals::new();
+
+        for range in &ok_messages {
+            if !range.is_empty() {
+                delivered.enqueue_range(range);
+            }
+        }
+
+        self.confirm(
+            topic_id,
+            queue_id,
+            confirmation_id,
+            connection_id,
+            Some(&delivered),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
+
+    use super::*;
+    use crate::MySbMessage;
+
+    struct TestCallback {
+        queue_type: TopicQueueType,
+        sender: UnboundedSender<(Vec<MySbMessage>, i64, i32)>,
+    }
+
+    #[async_trait::async_trait]
+    impl MyServiceBusSubscriberClientCallback for TestCallback {
+        fn get_topic_id(&self) -> &str {
+            "test-topic"
+        }
+
+        fn get_queue_id(&self) -> &str {
+            "test-queue"
+        }
+
+        fn get_queue_type(&self) -> TopicQueueType {
+            self.queue_type
+        }
+
+        async fn new_events(
+            &self,
+            messages_to_deliver: Vec<MySbMessage>,
+            confirmation_id: i64,
+            connection_id: i32,
+        ) {
+            self.sender
+                .send((messages_to_deliver, confirmation_id, connection_id))
+                .unwrap();
+        }
+    }
+
+    fn subscribe(
+        broker: &MyServiceBusInMemoryBroker,
+        queue_type: TopicQueueType,
+    ) -> (i32, UnboundedReceiver<(Vec<MySbMessage>, i64, i32)>) {
+        let (sender, receiver) = tokio::sync::mpsc::unbounded_channel();
+        let connection_id = broker.subscribe(Arc::new(TestCallback { queue_type, sender }));
+        (connection_id, receiver)
+    }
+
+    async fn publish(broker: &MyServiceBusInMemoryBroker, amount: usize) {
+        let messages: Vec<MessageToPublish> = (0..amount)
+            .map(|i| MessageToPublish::new(vec![i as u8]))
+            .collect();
+
+        broker
+            .publish_messages("test-topic", &messages, false)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_publish_deliver_and_confirm() {
+        let broker = MyServiceBusInMemoryBroker::new();
+        let (_, mut receiver) = subscribe(&broker, TopicQueueType::Permanent);
+
+        publish(&broker, 2).await;
+
+        let (messages, confirmation_id, connection_id) = receiver.recv().await.unwrap();
+
+        assert_eq!(2, messages.len());
+        assert_eq!(0, messages[0].id.get_value());
+        assert_eq!(1, messages[1].id.get_value());
+        assert_eq!(0, messages[0].attempt_no);
+
+        broker.confirm_delivery(
+            "test-topic",
+            "test-queue",
+            confirmation_id,
+            connection_id,
+            true,
+        );
+
+        assert_eq!(
+            0,
+            broker.get_queue_size("test-topic", "test-queue").unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_not_delivered_messages_are_redelivered() {
+        let broker = MyServiceBusInMemoryBroker::new();
+        let (_, mut receiver) = subscribe(&broker, TopicQueueType::Permanent);
+
+        publish(&broker, 2).await;
+
+        let (_, confirmation_id, connection_id) = receiver.recv().await.unwrap();
+
+        broker.confirm_delivery(
+            "test-topic",
+            "test-queue",
+            confirmation_id,
+            connection_id,
+            false,
+        );
+
+        let (messages, _, _) = receiver.recv().await.unwrap();
+
+        assert_eq!(2, messages.len());
+        assert_eq!(1, messages[0].attempt_no);
+        assert_eq!(1, messages[1].attempt_no);
+    }
+
+    #[tokio::test]
+    async fn test_some_messages_ok() {
+        let broker = MyServiceBusInMemoryBroker::new();
+        let (_, mut receiver) = subscribe(&broker, TopicQueueType::Permanent);
+
+        publish(&broker, 3).await;
+
+        let (_, confirmation_id, connection_id) = receiver.recv().await.unwrap();
+
+        broker.confirm_some_messages_ok(
+            "test-topic",
+            "test-queue",
+            confirmation_id,
+            connection_id,
+            vec![
+                QueueIndexRange::restore(0, 0),
+                QueueIndexRange::restore(2, 2),
+            ],
+        );
+
+        let (messages, _, _) = receiver.recv().await.unwrap();
+
+        assert_eq!(1, messages.len());
+        assert_eq!(1, messages[0].id.get_value());
+        assert_eq!(1, messages[0].attempt_no);
+    }
+
+    #[tokio::test]
+    async fn test_delete_on_disconnect_queue_is_removed() {
+        let broker = MyServiceBusInMemoryBroker::new();
+        let (connection_id, _receiver) = subscribe(&broker, TopicQueueType::DeleteOnDisconnect);
+
+        publish(&broker, 1).await;
+        assert_eq!(
+            1,
+            broker.get_queue_size("test-topic", "test-queue").unwrap()
+        );
+
+        broker.disconnect(connection_id);
+
+        assert_eq!(
+            true,
+            broker.get_queue_size("test-topic", "test-queue").is_none()
+        );
+    }
+}
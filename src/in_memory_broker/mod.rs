@@ -0,0 +1,6 @@
+mod in_memory_broker;
+mod queue_data;
+mod topic_data;
+pub use in_memory_broker::*;
+pub use queue_data::*;
+pub use topic_data::*;
@@ -0,0 +1,175 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+};
+
+use crate::{
+    queue_with_intervals::QueueWithIntervals, subscriber::TopicQueueType, MySbMessage,
+    MyServiceBusSubscriberClientCallback,
+};
+
+pub struct InMemorySubscriberConnection {
+    pub connection_id: i32,
+    pub callback: Arc<dyn MyServiceBusSubscriberClientCallback + Send + Sync + 'static>,
+    pub confirmation_id: Option<i64>,
+}
+
+pub struct InMemoryDelivery {
+    pub connection_id: i32,
+    pub ids: QueueWithIntervals,
+}
+
+pub struct InMemoryDeliveryToSend {
+    pub callback: Arc<dyn MyServiceBusSubscriberClientCallback + Send + Sync + 'static>,
+    pub messages: Vec<MySbMessage>,
+    pub confirmation_id: i64,
+    pub connection_id: i32,
+}
+
+pub struct InMemoryQueueData {
+    pub queue_id: String,
+    pub queue_type: TopicQueueType,
+    pub messages: BTreeMap<i64, MySbMessage>,
+    pub to_deliver: QueueWithIntervals,
+    pub on_delivery: HashMap<i64, InMemoryDelivery>,
+    pub subscribers: Vec<InMemorySubscriberConnection>,
+}
+
+impl InMemoryQueueData {
+    pub fn new(queue_id: String, queue_type: TopicQueueType) -> Self {
+        Self {
+            queue_id,
+            queue_type,
+            messages: BTreeMap::new(),
+            to_deliver: QueueWithIntervals::new(),
+            on_delivery: HashMap::new(),
+            subscribers: Vec::new(),
+        }
+    }
+
+    pub fn new_message(&mut self, message: MySbMessage) {
+        self.to_deliver.enqueue(message.id.get_value());
+        self.messages.insert(message.id.get_value(), message);
+    }
+
+    pub fn get_next_delivery(
+        &mut self,
+        confirmation_id: i64,
+        max_messages: usize,
+    ) -> Option<InMemoryDeliveryToSend> {
+        if self.to_deliver.len() == 0 {
+            return None;
+        }
+
+        let subscriber = self
+            .subscribers
+            .iter_mut()
+            .find(|itm| itm.confirmation_id.is_none())?;
+
+        let mut ids = QueueWithIntervals::new();
+        let mut messages = Vec::new();
+
+        while messages.len() < max_messages {
+            let id = match self.to_deliver.dequeue() {
+                Some(id) => id,
+                None => break,
+            };
+
+            if let Some(message) = self.messages.get(&id) {
+                ids.enqueue(id);
+                messages.push(message.clone());
+            }
+        }
+
+        if messages.is_empty() {
+            return None;
+        }
+
+        subscriber.confirmation_id = Some(confirmation_id);
+
+        self.on_delivery.insert(
+            confirmation_id,
+            InMemoryDelivery {
+                connection_id: subscriber.connection_id,
+                ids,
+            },
+        );
+
+        Some(InMemoryDeliveryToSend {
+            callback: subscriber.callback.clone(),
+            messages,
+            confirmation_id,
+            connection_id: subscriber.connection_id,
+        })
+    }
+
+    pub fn confirm(
+        &mut self,
+        confirmation_id: i64,
+        connection_id: i32,
+        delivered: Option<&QueueWithIntervals>,
+    ) -> bool {
+        match self.on_delivery.get(&confirmation_id) {
+            Some(delivery) => {
+                if delivery.connection_id != connection_id {
+                    return false;
+                }
+            }
+            None => return false,
+        }
+
+        let delivery = self.on_delivery.remove(&confirmation_id).unwrap();
+
+        for subscriber in self.subscribers.iter_mut() {
+            if subscriber.confirmation_id == Some(confirmation_id) {
+                subscriber.confirmation_id = None;
+            }
+        }
+
+        for id in &delivery.ids {
+            let is_delivered = match delivered {
+                Some(delivered) => delivered.has_message(id),
+                None => true,
+            };
+
+            if is_delivered {
+                self.messages.remove(&id);
+            } else {
+                self.return_to_queue(id);
+            }
+        }
+
+        true
+    }
+
+    pub fn disconnect(&mut self, connection_id: i32) -> bool {
+        let index = self
+            .subscribers
+            .iter()
+            .position(|itm| itm.connection_id == connection_id);
+
+        let index = match index {
+            Some(index) => index,
+            None => return false,
+        };
+
+        let subscriber = self.subscribers.remove(index);
+
+        if let Some(confirmation_id) = subscriber.confirmation_id {
+            if let Some(delivery) = self.on_delivery.remove(&confirmation_id) {
+                for id in &delivery.ids {
+                    self.return_to_queue(id);
+                }
+            }
+        }
+
+        true
+    }
+
+    fn return_to_queue(&mut self, id: i64) {
+        if let Some(message) = self.messages.get_mut(&id) {
+            message.attempt_no += 1;
+            self.to_deliver.enqueue(id);
+        }
+    }
+}
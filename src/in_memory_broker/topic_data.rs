@@ -0,0 +1,37 @@
+use std::collections::HashMap;
+
+use crate::{publisher::MessageToPublish, MessageId, MySbMessage};
+
+use super::InMemoryQueueData;
+
+pub struct InMemoryTopicData {
+    pub topic_id: String,
+    pub next_message_id: i64,
+    pub queues: HashMap<String, InMemoryQueueData>,
+}
+
+impl InMemoryTopicData {
+    pub fn new(topic_id: String) -> Self {
+        Self {
+            topic_id,
+            next_message_id: 0,
+            queues: HashMap::new(),
+        }
+    }
+
+    pub fn publish(&mut self, messages: &[MessageToPublish]) {
+        for message in messages {
+            let id = MessageId::new(self.next_message_id);
+            self.next_message_id += 1;
+
+            for queue in self.queues.values_mut() {
+                queue.new_message(MySbMessage {
+                    id,
+                    attempt_no: 0,
+                    headers: message.headers.clone(),
+                    content: message.content.clone(),
+                });
+            }
+        }
+    }
+}
@@ -1,10 +1,14 @@
 mod abstractions;
 mod errors;
+#[cfg(feature = "in-memory-broker")]
+pub mod in_memory_broker;
 mod message_id;
 mod my_sb_message;
 pub mod publisher;
 pub mod queue_with_intervals;
 pub mod subscriber;
+#[cfg(test)]
+mod test_utils;
 pub use abstractions::*;
 pub use errors::*;
 pub use message_id::*;
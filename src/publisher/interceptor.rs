@@ -0,0 +1,180 @@
+use std::sync::Arc;
+
+#[cfg(feature = "with-telemetry")]
+use my_telemetry::MyTelemetryContext;
+
+use crate::PublishError;
+
+use super::MessageToPublish;
+
+pub struct PublishContext<'s> {
+    pub topic_id: &'s str,
+    #[cfg(feature = "with-telemetry")]
+    pub telemetry_context: Option<&'s MyTelemetryContext>,
+}
+
+pub trait MyServiceBusPublishInterceptor {
+    fn before_publish(
+        &self,
+        _ctx: &PublishContext,
+        _message: &mut MessageToPublish,
+    ) -> Result<(), PublishError> {
+        Ok(())
+    }
+
+    fn after_publish(
+        &self,
+        _topic_id: &str,
+        _messages: &[MessageToPublish],
+        _result: &Result<(), PublishError>,
+    ) {
+    }
+}
+
+#[derive(Clone)]
+pub struct PublishInterceptors {
+    items: Vec<Arc<dyn MyServiceBusPublishInterceptor + Send + Sync + 'static>>,
+    has_custom_interceptors: bool,
+}
+
+impl PublishInterceptors {
+    pub fn new() -> Self {
+        #[cfg(not(feature = "with-telemetry"))]
+        let items = Vec::new();
+
+        #[cfg(feature = "with-telemetry")]
+        let items: Vec<Arc<dyn MyServiceBusPublishInterceptor + Send + Sync + 'static>> =
+            vec![Arc::new(super::my_telemetry::MyTelemetryPublishInterceptor)];
+
+        Self {
+            items,
+            has_custom_interceptors: false,
+        }
+    }
+
+    pub fn new_empty() -> Self {
+        Self {
+            items: Vec::new(),
+            has_custom_interceptors: false,
+        }
+    }
+
+    pub fn add(
+        &mut self,
+        interceptor: Arc<dyn MyServiceBusPublishInterceptor + Send + Sync + 'static>,
+    ) {
+        self.items.push(interceptor);
+        self.has_custom_interceptors = true;
+    }
+
+    pub fn has_custom_interceptors(&self) -> bool {
+        self.has_custom_interceptors
+    }
+
+    pub fn before_publish(
+        &self,
+        ctx: &PublishContext,
+        message: &mut MessageToPublish,
+    ) -> Result<(), PublishError> {
+        for interceptor in &self.items {
+            interceptor.before_publish(ctx, message)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn after_publish(
+        &self,
+        topic_id: &str,
+        messages: &[MessageToPublish],
+        result: &Result<(), PublishError>,
+    ) {
+        for interceptor in &self.items {
+            interceptor.after_publish(topic_id, messages, result);
+        }
+    }
+}
+
+impl Default for PublishInterceptors {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl std::fmt::Debug for PublishInterceptors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PublishInterceptors")
+            .field("amount", &self.items.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::test_utils::TestInterceptor;
+
+    fn create_interceptors(
+        calls: &Arc<Mutex<Vec<String>>>,
+        reject_first: bool,
+    ) -> PublishInterceptors {
+        let mut interceptors = PublishInterceptors::new_empty();
+
+        interceptors.add(Arc::new(TestInterceptor {
+            name: "a",
+            calls: calls.clone(),
+            reject: reject_first,
+        }));
+
+        interceptors.add(Arc::new(TestInterceptor {
+            name: "b",
+            calls: calls.clone(),
+            reject: false,
+        }));
+
+        interceptors
+    }
+
+    fn create_ctx() -> PublishContext<'static> {
+        PublishContext {
+            topic_id: "test-topic",
+            #[cfg(feature = "with-telemetry")]
+            telemetry_context: None,
+        }
+    }
+
+    #[test]
+    fn test_interceptors_are_called_in_order_and_mutate_message() {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let interceptors = create_interceptors(&calls, false);
+
+        let mut message = MessageToPublish::new(vec![0]);
+        interceptors
+            .before_publish(&create_ctx(), &mut message)
+            .unwrap();
+
+        assert_eq!(vec![0, b'a', b'b'], message.content);
+
+        interceptors.after_publish("test-topic", &[message], &Ok(()));
+
+        assert_eq!(
+            vec!["a:before", "b:before", "a:after:1", "b:after:1"],
+            *calls.lock().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_rejection_stops_the_chain() {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let interceptors = create_interceptors(&calls, true);
+
+        let mut message = MessageToPublish::new(vec![0]);
+        let result = interceptors.before_publish(&create_ctx(), &mut message);
+
+        assert!(matches!(result, Err(PublishError::Other(_))));
+        assert_eq!(vec![0], message.content);
+        assert_eq!(vec!["a:before"], *calls.lock().unwrap());
+    }
+}
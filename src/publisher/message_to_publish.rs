@@ -19,4 +19,16 @@ impl MessageToPublish {
             content,
         }
     }
+
+    pub fn get_size_in_bytes(&self) -> usize {
+        let headers_size: usize = match self.headers.as_ref() {
+            Some(headers) => headers
+                .iter()
+                .map(|(key, value)| key.len() + value.len())
+                .sum(),
+            None => 0,
+        };
+
+        self.content.len() + headers_size
+    }
 }
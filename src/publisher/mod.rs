@@ -1,3 +1,4 @@
+mod interceptor;
 mod message_to_publish;
 #[cfg(feature = "with-telemetry")]
 mod my_telemetry;
@@ -5,7 +6,10 @@ mod my_telemetry;
 mod publisher;
 mod serializer;
 mod with_internal_queue;
+pub use interceptor::*;
 pub use message_to_publish::*;
+#[cfg(feature = "with-telemetry")]
+pub use my_telemetry::MyTelemetryPublishInterceptor;
 pub use publisher::*;
 pub use serializer::*;
 pub use with_internal_queue::*;
@@ -2,6 +2,10 @@ use std::collections::HashMap;
 
 use my_telemetry::MyTelemetryContext;
 
+use crate::PublishError;
+
+use super::{MessageToPublish, MyServiceBusPublishInterceptor, PublishContext};
+
 pub fn apply_publish_telemetry(
     headers: &mut Option<HashMap<String, String>>,
     my_telemetry: &MyTelemetryContext,
@@ -15,3 +19,19 @@ pub fn apply_publish_telemetry(
         my_telemetry.as_string(),
     );
 }
+
+pub struct MyTelemetryPublishInterceptor;
+
+impl MyServiceBusPublishInterceptor for MyTelemetryPublishInterceptor {
+    fn before_publish(
+        &self,
+        ctx: &PublishContext,
+        message: &mut MessageToPublish,
+    ) -> Result<(), PublishError> {
+        if let Some(my_telemetry) = ctx.telemetry_context {
+            apply_publish_telemetry(&mut message.headers, my_telemetry);
+        }
+
+        Ok(())
+    }
+}
@@ -6,7 +6,7 @@ use rust_extensions::Logger;
 
 use crate::{MyServiceBusPublisherClient, PublishError};
 
-use super::{MessageToPublish, MySbMessageSerializer};
+use super::{MessageToPublish, MySbMessageSerializer, PublishContext, PublishInterceptors};
 
 pub struct MyServiceBusPublisher<TMessageModel: MySbMessageSerializer> {
     pub topic_id: String,
@@ -14,6 +14,7 @@ pub struct MyServiceBusPublisher<TMessageModel: MySbMessageSerializer> {
     pub do_retries: bool,
     pub itm: Option<TMessageModel>,
     pub logger: Arc<dyn Logger + Send + Sync + 'static>,
+    pub interceptors: PublishInterceptors,
 }
 
 impl<TMessageModel: MySbMessageSerializer> MyServiceBusPublisher<TMessageModel> {
@@ -29,102 +30,105 @@ impl<TMessageModel: MySbMessageSerializer> MyServiceBusPublisher<TMessageModel>
             do_retries,
             logger,
             itm: None,
+            interceptors: PublishInterceptors::new(),
         }
     }
 
-    pub async fn publish(
+    fn serialize(
         &self,
+        process: &str,
         message: &TMessageModel,
+        headers: Option<HashMap<String, String>>,
         #[cfg(feature = "with-telemetry")] telemetry_context: Option<&MyTelemetryContext>,
-    ) -> Result<(), PublishError> {
-        let content = message.serialize(None);
+    ) -> Result<MessageToPublish, PublishError> {
+        let content = message.serialize(headers);
 
         if let Err(err) = content {
             let mut ctx = HashMap::new();
             ctx.insert("topicId".to_string(), self.topic_id.to_string());
             self.logger
-                .write_fatal_error("publish".to_string(), err.clone(), Some(ctx));
+                .write_fatal_error(process.to_string(), err.clone(), Some(ctx));
+
             return Err(PublishError::SerializationError(err));
         }
 
-        #[cfg(not(feature = "with-telemetry"))]
         let (content, headers) = content.unwrap();
 
-        #[cfg(feature = "with-telemetry")]
-        let (content, mut headers) = content.unwrap();
+        let mut result = MessageToPublish { headers, content };
 
-        #[cfg(feature = "with-telemetry")]
-        if let Some(my_telemetry) = telemetry_context.as_ref() {
-            super::my_telemetry::apply_publish_telemetry(&mut headers, my_telemetry)
-        }
-
-        let result = self
-            .client
-            .publish_message(
-                &self.topic_id,
-                MessageToPublish { headers, content },
-                self.do_retries,
-            )
-            .await;
+        let publish_ctx = PublishContext {
+            topic_id: &self.topic_id,
+            #[cfg(feature = "with-telemetry")]
+            telemetry_context,
+        };
 
-        if let Err(err) = &result {
+        if let Err(err) = self.interceptors.before_publish(&publish_ctx, &mut result) {
             let mut ctx = HashMap::new();
             ctx.insert("topicId".to_string(), self.topic_id.to_string());
             self.logger.write_error(
-                "publish".to_string(),
-                format!("Can not publish message. Error: {:?}", err),
+                process.to_string(),
+                format!("Message is rejected by interceptor. Error: {:?}", err),
                 Some(ctx),
             );
+
+            return Err(err);
         }
 
-        result
+        Ok(result)
     }
 
-    pub async fn publish_with_headers(
+    async fn publish_message_to_client(
         &self,
-        message: &TMessageModel,
-        headers: HashMap<String, String>,
-        #[cfg(feature = "with-telemetry")] telemetry_context: Option<&MyTelemetryContext>,
+        process: &str,
+        message: MessageToPublish,
     ) -> Result<(), PublishError> {
-        let content = message.serialize(Some(headers));
+        let to_observe = if self.interceptors.has_custom_interceptors() {
+            Some(message.clone())
+        } else {
+            None
+        };
 
-        if let Err(err) = content {
-            let mut ctx = HashMap::new();
-            ctx.insert("topicId".to_string(), self.topic_id.to_string());
-            self.logger.write_fatal_error(
-                "publish_with_headers".to_string(),
-                err.clone(),
-                Some(ctx),
-            );
+        let result = self
+            .client
+            .publish_message(&self.topic_id, message, self.do_retries)
+            .await;
 
-            return Err(PublishError::SerializationError(err));
+        if let Some(message) = to_observe {
+            self.interceptors
+                .after_publish(&self.topic_id, &[message], &result);
         }
 
-        #[cfg(not(feature = "with-telemetry"))]
-        let (content, headers) = content.unwrap();
-
-        #[cfg(feature = "with-telemetry")]
-        let (content, mut headers) = content.unwrap();
-
-        #[cfg(feature = "with-telemetry")]
-        if let Some(my_telemetry) = telemetry_context.as_ref() {
-            super::my_telemetry::apply_publish_telemetry(&mut headers, my_telemetry)
-        }
+        self.handle_publish_result(process, result)
+    }
 
+    async fn publish_messages_to_client(
+        &self,
+        process: &str,
+        messages: Vec<MessageToPublish>,
+    ) -> Result<(), PublishError> {
         let result = self
             .client
-            .publish_message(
-                &self.topic_id,
-                MessageToPublish { headers, content },
-                self.do_retries,
-            )
+            .publish_messages(&self.topic_id, &messages, self.do_retries)
             .await;
 
+        if self.interceptors.has_custom_interceptors() {
+            self.interceptors
+                .after_publish(&self.topic_id, &messages, &result);
+        }
+
+        self.handle_publish_result(process, result)
+    }
+
+    fn handle_publish_result(
+        &self,
+        process: &str,
+        result: Result<(), PublishError>,
+    ) -> Result<(), PublishError> {
         if let Err(err) = &result {
             let mut ctx = HashMap::new();
             ctx.insert("topicId".to_string(), self.topic_id.to_string());
             self.logger.write_error(
-                "publish_messages".to_string(),
+                process.to_string(),
                 format!("Can not publish message. Error: {:?}", err),
                 Some(ctx),
             );
@@ -133,58 +137,59 @@ impl<TMessageModel: MySbMessageSerializer> MyServiceBusPublisher<TMessageModel>
         result
     }
 
-    pub async fn publish_messages(
+    pub async fn publish(
         &self,
-        messages: &[TMessageModel],
+        message: &TMessageModel,
         #[cfg(feature = "with-telemetry")] telemetry_context: Option<&MyTelemetryContext>,
     ) -> Result<(), PublishError> {
-        let mut messages_to_publish = Vec::with_capacity(messages.len());
-
-        for message in messages {
-            let content = message.serialize(None);
-
-            if let Err(err) = content {
-                let mut ctx = HashMap::new();
-                ctx.insert("topicId".to_string(), self.topic_id.to_string());
-                self.logger.write_fatal_error(
-                    "publish_messages".to_string(),
-                    err.clone(),
-                    Some(ctx),
-                );
-
-                return Err(PublishError::SerializationError(err));
-            }
-
-            #[cfg(not(feature = "with-telemetry"))]
-            let (content, headers) = content.unwrap();
-
+        let message = self.serialize(
+            "publish",
+            message,
+            None,
             #[cfg(feature = "with-telemetry")]
-            let (content, mut headers) = content.unwrap();
+            telemetry_context,
+        )?;
+
+        self.publish_message_to_client("publish", message).await
+    }
 
+    pub async fn publish_with_headers(
+        &self,
+        message: &TMessageModel,
+        headers: HashMap<String, String>,
+        #[cfg(feature = "with-telemetry")] telemetry_context: Option<&MyTelemetryContext>,
+    ) -> Result<(), PublishError> {
+        let message = self.serialize(
+            "publish_with_headers",
+            message,
+            Some(headers),
             #[cfg(feature = "with-telemetry")]
-            if let Some(my_telemetry) = telemetry_context.as_ref() {
-                super::my_telemetry::apply_publish_telemetry(&mut headers, my_telemetry)
-            }
+            telemetry_context,
+        )?;
 
-            messages_to_publish.push(MessageToPublish { headers, content });
-        }
+        self.publish_message_to_client("publish_with_headers", message)
+            .await
+    }
 
-        let result = self
-            .client
-            .publish_messages(&self.topic_id, &messages_to_publish, self.do_retries)
-            .await;
+    pub async fn publish_messages(
+        &self,
+        messages: &[TMessageModel],
+        #[cfg(feature = "with-telemetry")] telemetry_context: Option<&MyTelemetryContext>,
+    ) -> Result<(), PublishError> {
+        let mut messages_to_publish = Vec::with_capacity(messages.len());
 
-        if let Err(err) = &result {
-            let mut ctx = HashMap::new();
-            ctx.insert("topicId".to_string(), self.topic_id.to_string());
-            self.logger.write_error(
-                "publish_messages".to_string(),
-                format!("Can not publish message. Error: {:?}", err),
-                Some(ctx),
-            );
+        for message in messages {
+            messages_to_publish.push(self.serialize(
+                "publish_messages",
+                message,
+                None,
+                #[cfg(feature = "with-telemetry")]
+                telemetry_context,
+            )?);
         }
 
-        result
+        self.publish_messages_to_client("publish_messages", messages_to_publish)
+            .await
     }
 
     pub async fn publish_messages_with_header(
@@ -195,49 +200,97 @@ impl<TMessageModel: MySbMessageSerializer> MyServiceBusPublisher<TMessageModel>
         let mut messages_to_publish = Vec::with_capacity(messages.len());
 
         for (contract, headers) in messages {
-            let content = contract.serialize(headers);
-
-            if let Err(err) = content {
-                let mut ctx = HashMap::new();
-                ctx.insert("topicId".to_string(), self.topic_id.to_string());
-                self.logger.write_fatal_error(
-                    "publish_messages_with_header".to_string(),
-                    err.clone(),
-                    Some(ctx),
-                );
-
-                return Err(PublishError::SerializationError(err));
-            }
-
-            #[cfg(not(feature = "with-telemetry"))]
-            let (content, headers) = content.unwrap();
+            messages_to_publish.push(self.serialize(
+                "publish_messages_with_header",
+                &contract,
+                headers,
+                #[cfg(feature = "with-telemetry")]
+                telemetry_context,
+            )?);
+        }
 
-            #[cfg(feature = "with-telemetry")]
-            let (content, mut headers) = content.unwrap();
+        self.publish_messages_to_client("publish_messages_with_header", messages_to_publish)
+            .await
+    }
+}
 
-            #[cfg(feature = "with-telemetry")]
-            if let Some(my_telemetry) = telemetry_context.as_ref() {
-                super::my_telemetry::apply_publish_telemetry(&mut headers, my_telemetry)
-            }
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::test_utils::{TestInterceptor, TestLogger, TestPublishMessage, TestPublisherClient};
+
+    fn create_publisher(
+        client: &Arc<TestPublisherClient>,
+        logger: &Arc<TestLogger>,
+        calls: &Arc<Mutex<Vec<String>>>,
+        reject: bool,
+    ) -> MyServiceBusPublisher<TestPublishMessage> {
+        let mut publisher = MyServiceBusPublisher::new(
+            "test-topic".to_string(),
+            client.clone(),
+            true,
+            logger.clone(),
+        );
+
+        publisher.interceptors.add(Arc::new(TestInterceptor {
+            name: "a",
+            calls: calls.clone(),
+            reject,
+        }));
+
+        publisher
+    }
 
-            messages_to_publish.push(MessageToPublish { content, headers });
-        }
+    #[tokio::test]
+    async fn test_after_publish_is_called_for_single_and_batch_publish() {
+        let client = TestPublisherClient::new();
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let publisher = create_publisher(&client, &TestLogger::new(), &calls, false);
+
+        publisher
+            .publish(
+                &TestPublishMessage(1),
+                #[cfg(feature = "with-telemetry")]
+                None,
+            )
+            .await
+            .unwrap();
+
+        publisher
+            .publish_messages(
+                &[TestPublishMessage(2), TestPublishMessage(3)],
+                #[cfg(feature = "with-telemetry")]
+                None,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            vec!["a:before", "a:after:1", "a:before", "a:before", "a:after:2"],
+            *calls.lock().unwrap()
+        );
+        assert_eq!(vec![1, 2, 3], client.get_published_contents());
+    }
 
-        let result = self
-            .client
-            .publish_messages(&self.topic_id, &messages_to_publish, self.do_retries)
+    #[tokio::test]
+    async fn test_rejected_message_is_logged_and_not_published() {
+        let client = TestPublisherClient::new();
+        let logger = TestLogger::new();
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let publisher = create_publisher(&client, &logger, &calls, true);
+
+        let result = publisher
+            .publish(
+                &TestPublishMessage(1),
+                #[cfg(feature = "with-telemetry")]
+                None,
+            )
             .await;
 
-        if let Err(err) = &result {
-            let mut ctx = HashMap::new();
-            ctx.insert("topicId".to_string(), self.topic_id.to_string());
-            self.logger.write_error(
-                "publish_messages_with_header".to_string(),
-                format!("Can not publish message. Error: {:?}", err),
-                Some(ctx),
-            );
-        }
-
-        result
+        assert!(matches!(result, Err(PublishError::Other(_))));
+        assert_eq!(true, logger.has_message("rejected by interceptor"));
+        assert_eq!(0, client.get_attempts());
     }
 }
@@ -1,71 +1,521 @@
 use std::{
     collections::{HashMap, VecDeque},
-    sync::Arc,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
 };
 
-use tokio::sync::Mutex;
+use tokio::sync::{Mutex, Notify};
 
-use crate::MyServiceBusPublisherClient;
+use crate::{MyServiceBusPublisherClient, PublishError};
 
-use super::super::MessageToPublish;
+use super::{
+    super::MessageToPublish, DiskSpoolError, PublishConfirmationState,
+    PublisherQueueRemainingCapacity, PublisherWithInternalQueueSettings, QueueOverflowPolicy,
+    RetryDelayRandom, SharedDiskSpool,
+};
+
+pub struct QueueToPublishItem {
+    pub message: MessageToPublish,
+    pub confirmation: Option<Arc<PublishConfirmationState>>,
+    pub spool_seq: Option<u64>,
+}
+
+impl QueueToPublishItem {
+    pub fn set_error(&self, err: PublishError) {
+        if let Some(confirmation) = self.confirmation.as_ref() {
+            confirmation.set_error(err);
+        }
+    }
+}
+
+pub struct InFlightBatch {
+    pub messages_amount: usize,
+    pub size_in_bytes: usize,
+    pub confirmations: Vec<Arc<PublishConfirmationState>>,
+    pub spool_seqs: Vec<u64>,
+}
+
+pub struct BatchToPublish {
+    pub batch_id: u64,
+    pub messages: Vec<MessageToPublish>,
+}
 
 pub struct QueueToPublish {
-    pub queue: VecDeque<MessageToPublish>,
+    pub queue: VecDeque<QueueToPublishItem>,
+    pub queue_size_in_bytes: usize,
     pub being_published: usize,
+    pub being_published_size_in_bytes: usize,
+    pub in_flight_batches: HashMap<u64, InFlightBatch>,
+    pub next_batch_id: u64,
+    pub reserved_messages: usize,
+    pub reserved_size_in_bytes: usize,
+    pub is_closed: bool,
+    pub spool: Option<SharedDiskSpool>,
 }
 
 impl QueueToPublish {
     pub fn new() -> Self {
         Self {
             queue: VecDeque::new(),
+            queue_size_in_bytes: 0,
             being_published: 0,
+            being_published_size_in_bytes: 0,
+            in_flight_batches: HashMap::new(),
+            next_batch_id: 0,
+            reserved_messages: 0,
+            reserved_size_in_bytes: 0,
+            is_closed: false,
+            spool: None,
         }
     }
+
+    pub fn get_messages_amount(&self) -> usize {
+        self.queue.len() + self.being_published + self.reserved_messages
+    }
+
+    pub fn get_size_in_bytes(&self) -> usize {
+        self.queue_size_in_bytes + self.being_published_size_in_bytes + self.reserved_size_in_bytes
+    }
+
+    pub fn has_room_for(
+        &self,
+        settings: &PublisherWithInternalQueueSettings,
+        messages_amount: usize,
+        size_in_bytes: usize,
+    ) -> bool {
+        if let Some(max_messages) = settings.max_messages {
+            if self.get_messages_amount() + messages_amount > max_messages {
+                return false;
+            }
+        }
+
+        if let Some(max_bytes) = settings.max_bytes {
+            if self.get_size_in_bytes() + size_in_bytes > max_bytes {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    pub fn get_remaining_capacity(
+        &self,
+        settings: &PublisherWithInternalQueueSettings,
+    ) -> PublisherQueueRemainingCapacity {
+        PublisherQueueRemainingCapacity {
+            messages: settings
+                .max_messages
+                .map(|max| max.saturating_sub(self.get_messages_amount())),
+            bytes: settings
+                .max_bytes
+                .map(|max| max.saturating_sub(self.get_size_in_bytes())),
+        }
+    }
+
+    pub fn reserve(&mut self, messages_amount: usize, size_in_bytes: usize) {
+        self.reserved_messages += messages_amount;
+        self.reserved_size_in_bytes += size_in_bytes;
+    }
+
+    pub fn release_reservation(&mut self, messages_amount: usize, size_in_bytes: usize) {
+        self.reserved_messages -= messages_amount;
+        self.reserved_size_in_bytes -= size_in_bytes;
+    }
+
+    pub fn push(&mut self, item: QueueToPublishItem) {
+        self.queue_size_in_bytes += item.message.get_size_in_bytes();
+        self.queue.push_back(item);
+    }
+
+    pub fn pop_front(&mut self) -> Option<QueueToPublishItem> {
+        let item = self.queue.pop_front()?;
+        self.queue_size_in_bytes -= item.message.get_size_in_bytes();
+        Some(item)
+    }
 }
 
 pub struct PublisherWithInternalQueueData {
     pub topic_id: String,
     pub client: Arc<dyn MyServiceBusPublisherClient + Send + Sync + 'static>,
     pub queue_to_publish: Mutex<QueueToPublish>,
+    pub settings: PublisherWithInternalQueueSettings,
+    pub queue_is_released: Notify,
+    pub is_shutting_down: AtomicBool,
+    pub retry_delay_random: RetryDelayRandom,
 
     pub logger: Arc<dyn rust_extensions::Logger + Send + Sync + 'static>,
 }
 
 impl PublisherWithInternalQueueData {
-    pub async fn get_messages_to_publish(&self) -> Option<Vec<MessageToPublish>> {
+    pub async fn enqueue(&self, items: Vec<QueueToPublishItem>) -> Result<(), PublishError> {
+        let messages_amount = items.len();
+        let size_in_bytes: usize = items
+            .iter()
+            .map(|itm| itm.message.get_size_in_bytes())
+            .sum();
+
+        if !self.settings.can_ever_fit(messages_amount, size_in_bytes) {
+            for item in &items {
+                item.set_error(PublishError::QueueIsFull);
+            }
+
+            return Err(PublishError::QueueIsFull);
+        }
+
+        let mut dropped_spool_seqs = Vec::new();
+
+        loop {
+            let queue_is_released = self.queue_is_released.notified();
+            tokio::pin!(queue_is_released);
+            queue_is_released.as_mut().enable();
+
+            let mut write_access = self.queue_to_publish.lock().await;
+
+            if self.is_shutting_down.load(Ordering::SeqCst) {
+                for item in &items {
+                    item.set_error(PublishError::PublisherIsStopped);
+                }
+
+                return Err(PublishError::PublisherIsStopped);
+            }
+
+            if !write_access.has_room_for(&self.settings, messages_amount, size_in_bytes) {
+                match self.settings.overflow_policy {
+                    QueueOverflowPolicy::Wait => {
+                        drop(write_access);
+                        queue_is_released.await;
+                        continue;
+                    }
+                    QueueOverflowPolicy::Reject => {
+                        for item in &items {
+                            item.set_error(PublishError::QueueIsFull);
+                        }
+
+                        return Err(PublishError::QueueIsFull);
+                    }
+                    QueueOverflowPolicy::DropNewest => {
+                        for item in &items {
+                            item.set_error(PublishError::QueueIsFull);
+                        }
+
+                        self.write_dropped_messages(messages_amount, "Dropped new messages");
+                        return Err(PublishError::QueueIsFull);
+                    }
+                    QueueOverflowPolicy::DropOldest => {
+                        let mut dropped = Vec::new();
+
+                        while !write_access.has_room_for(
+                            &self.settings,
+                            messages_amount,
+                            size_in_bytes,
+                        ) {
+                            match write_access.pop_front() {
+                                Some(item) => {
+                                    item.set_error(PublishError::QueueIsFull);
+                                    dropped.push(item.spool_seq);
+                                }
+                                None => break,
+                            }
+                        }
+
+                        if !dropped.is_empty() {
+                            self.write_dropped_messages(dropped.len(), "Dropped oldest messages");
+                            dropped_spool_seqs.extend(dropped.into_iter().flatten());
+                        }
+
+                        if !write_access.has_room_for(
+                            &self.settings,
+                            messages_amount,
+                            size_in_bytes,
+                        ) {
+                            drop(write_access);
+                            self.remove_from_spool(dropped_spool_seqs, "enqueue").await;
+                            dropped_spool_seqs = Vec::new();
+                            queue_is_released.await;
+                            continue;
+                        }
+                    }
+                }
+            }
+
+            let spool = match write_access.spool.clone() {
+                Some(spool) => spool,
+                None => {
+                    for item in items {
+                        write_access.push(item);
+                    }
+
+                    drop(write_access);
+                    self.remove_from_spool(dropped_spool_seqs, "enqueue").await;
+
+                    return Ok(());
+                }
+            };
+
+            write_access.reserve(messages_amount, size_in_bytes);
+            drop(write_access);
+
+            let messages: Vec<MessageToPublish> =
+                items.iter().map(|itm| itm.message.clone()).collect();
+
+            let result = spool
+                .run(move |spool| {
+                    messages
+                        .iter()
+                        .map(|message| spool.append(message))
+                        .collect::<Vec<_>>()
+                })
+                .await;
+
+            let mut write_access = self.queue_to_publish.lock().await;
+            write_access.release_reservation(messages_amount, size_in_bytes);
+
+            let spool_seqs = match result {
+                Ok(spool_seqs) => spool_seqs,
+                Err(err) => {
+                    drop(write_access);
+                    self.queue_is_released.notify_waiters();
+                    self.write_spool_error("enqueue", err);
+
+                    for item in &items {
+                        item.set_error(PublishError::Other(
+                            "Can not write messages to disk spool".to_string(),
+                        ));
+                    }
+
+                    self.remove_from_spool(dropped_spool_seqs, "enqueue").await;
+
+                    return Err(PublishError::Other(
+                        "Can not write messages to disk spool".to_string(),
+                    ));
+                }
+            };
+
+            if write_access.is_closed {
+                drop(write_access);
+                self.queue_is_released.notify_waiters();
+
+                for item in &items {
+                    item.set_error(PublishError::PublisherIsStopped);
+                }
+
+                self.remove_from_spool(dropped_spool_seqs, "enqueue").await;
+
+                return Err(PublishError::PublisherIsStopped);
+            }
+
+            for (mut item, spool_seq) in items.into_iter().zip(spool_seqs) {
+                match spool_seq {
+                    Ok(seq) => item.spool_seq = Some(seq),
+                    Err(err) => self.write_spool_error("enqueue", err),
+                }
+
+                write_access.push(item);
+            }
+
+            drop(write_access);
+            self.remove_from_spool(dropped_spool_seqs, "enqueue").await;
+
+            return Ok(());
+        }
+    }
+
+    async fn remove_from_spool(&self, spool_seqs: Vec<u64>, process: &str) {
+        if spool_seqs.is_empty() {
+            return;
+        }
+
+        let spool = match self.queue_to_publish.lock().await.spool.clone() {
+            Some(spool) => spool,
+            None => return,
+        };
+
+        let result = spool
+            .run(move |spool| {
+                for seq in spool_seqs {
+                    spool.mark_as_published(seq);
+                }
+
+                spool.commit()
+            })
+            .await;
+
+        if let Err(err) = result.and_then(|result| result) {
+            self.write_spool_error(process, err);
+        }
+    }
+
+    fn write_dropped_messages(&self, amount: usize, reason: &str) {
+        let mut ctx = HashMap::new();
+        ctx.insert("topicId".to_string(), self.topic_id.to_string());
+        self.logger.write_error(
+            "enqueue".to_string(),
+            format!("Internal queue is full. {}: {}", reason, amount),
+            Some(ctx),
+        );
+    }
+
+    pub fn write_spool_error(&self, process: &str, err: DiskSpoolError) {
+        let mut ctx = HashMap::new();
+        ctx.insert("topicId".to_string(), self.topic_id.to_string());
+        self.logger.write_fatal_error(
+            process.to_string(),
+            format!("Disk spool error: {:?}", err),
+            Some(ctx),
+        );
+    }
+
+    pub async fn get_messages_to_publish(&self) -> Option<BatchToPublish> {
         let mut write_access = self.queue_to_publish.lock().await;
         if write_access.queue.len() == 0 {
             return None;
         }
 
-        let mut result = Vec::new();
+        let mut messages = Vec::new();
+        let mut in_flight_batch = InFlightBatch {
+            messages_amount: 0,
+            size_in_bytes: 0,
+            confirmations: Vec::new(),
+            spool_seqs: Vec::new(),
+        };
+
+        while !self
+            .settings
+            .publish_policy
+            .is_batch_full(messages.len(), in_flight_batch.size_in_bytes)
+        {
+            if let Some(item) = write_access.pop_front() {
+                in_flight_batch.size_in_bytes += item.message.get_size_in_bytes();
+                messages.push(item.message);
 
-        let mut size_to_publish = 0;
+                if let Some(spool_seq) = item.spool_seq {
+                    in_flight_batch.spool_seqs.push(spool_seq);
+                }
 
-        while size_to_publish < 4_000_000 {
-            if let Some(item) = write_access.queue.pop_front() {
-                size_to_publish += item.content.len();
-                result.push(item);
-                write_access.being_published += 1;
+                if let Some(confirmation) = item.confirmation {
+                    in_flight_batch.confirmations.push(confirmation);
+                }
             } else {
                 break;
             }
         }
 
-        Some(result)
+        in_flight_batch.messages_amount = messages.len();
+
+        write_access.being_published += in_flight_batch.messages_amount;
+        write_access.being_published_size_in_bytes += in_flight_batch.size_in_bytes;
+
+        let batch_id = write_access.next_batch_id;
+        write_access.next_batch_id += 1;
+        write_access
+            .in_flight_batches
+            .insert(batch_id, in_flight_batch);
+
+        Some(BatchToPublish { batch_id, messages })
+    }
+
+    pub async fn messages_are_published(&self, batch_id: u64) {
+        let spool_seqs = {
+            let read_access = self.queue_to_publish.lock().await;
+            read_access
+                .in_flight_batches
+                .get(&batch_id)
+                .map(|itm| itm.spool_seqs.clone())
+        };
+
+        if let Some(spool_seqs) = spool_seqs {
+            self.remove_from_spool(spool_seqs, "messages_are_published")
+                .await;
+        }
+
+        let mut write_access = self.queue_to_publish.lock().await;
+
+        let in_flight_batch = match write_access.in_flight_batches.remove(&batch_id) {
+            Some(in_flight_batch) => in_flight_batch,
+            None => return,
+        };
+
+        write_access.being_published -= in_flight_batch.messages_amount;
+        write_access.being_published_size_in_bytes -= in_flight_batch.size_in_bytes;
+
+        for confirmation in in_flight_batch.confirmations {
+            confirmation.message_is_published();
+        }
+
+        self.queue_is_released.notify_waiters();
+    }
+
+    pub async fn is_waiting_for_batch_to_fill(&self) -> bool {
+        let read_access = self.queue_to_publish.lock().await;
+
+        if read_access.queue.is_empty() {
+            return false;
+        }
+
+        !self
+            .settings
+            .publish_policy
+            .is_batch_full(read_access.queue.len(), read_access.queue_size_in_bytes)
+    }
+
+    pub async fn wait_until_everything_is_published(&self) {
+        loop {
+            let queue_is_released = self.queue_is_released.notified();
+            tokio::pin!(queue_is_released);
+            queue_is_released.as_mut().enable();
+
+            {
+                let read_access = self.queue_to_publish.lock().await;
+                if read_access.get_messages_amount() == 0 {
+                    return;
+                }
+            }
+
+            queue_is_released.await;
+        }
     }
 
-    pub async fn messages_are_published(&self) {
+    pub async fn fail_not_published_messages(&self) -> usize {
         let mut write_access = self.queue_to_publish.lock().await;
+        let result = write_access.get_messages_amount();
+        write_access.is_closed = true;
+
+        while let Some(item) = write_access.pop_front() {
+            item.set_error(PublishError::PublisherIsStopped);
+        }
+
+        for (_, in_flight_batch) in write_access.in_flight_batches.drain() {
+            for confirmation in in_flight_batch.confirmations {
+                confirmation.set_error(PublishError::PublisherIsStopped);
+            }
+        }
+
         write_access.being_published = 0;
+        write_access.being_published_size_in_bytes = 0;
+
+        self.queue_is_released.notify_waiters();
+
+        result
     }
 
     pub async fn publish(&self, to_publish: &[MessageToPublish]) -> bool {
         let result = self
             .client
-            .publish_messages(&self.topic_id, &to_publish, true)
+            .publish_messages(
+                &self.topic_id,
+                &to_publish,
+                self.settings.publish_policy.do_retry,
+            )
             .await;
 
+        if self.settings.interceptors.has_custom_interceptors() {
+            self.settings
+                .interceptors
+                .after_publish(&self.topic_id, to_publish, &result);
+        }
+
         match result {
             Ok(_) => return true,
             Err(err) => {
@@ -82,3 +532,217 @@ impl PublisherWithInternalQueueData {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::test_utils::{TestLogger, TestPublisherClient};
+
+    fn create_item(size: usize) -> QueueToPublishItem {
+        QueueToPublishItem {
+            message: MessageToPublish::new(vec![0; size]),
+            confirmation: None,
+            spool_seq: None,
+        }
+    }
+
+    fn create_numbered_item(no: u8) -> (QueueToPublishItem, super::super::PublishConfirmation) {
+        let (confirmation_state, confirmation) = PublishConfirmationState::new(1);
+
+        let item = QueueToPublishItem {
+            message: MessageToPublish::new(vec![no]),
+            confirmation: Some(Arc::new(confirmation_state)),
+            spool_seq: None,
+        };
+
+        (item, confirmation)
+    }
+
+    fn create_data(
+        max_messages: usize,
+        overflow_policy: QueueOverflowPolicy,
+    ) -> PublisherWithInternalQueueData {
+        PublisherWithInternalQueueData {
+            topic_id: "test-topic".to_string(),
+            client: TestPublisherClient::new(),
+            queue_to_publish: Mutex::new(QueueToPublish::new()),
+            settings: PublisherWithInternalQueueSettings {
+                max_messages: Some(max_messages),
+                overflow_policy,
+                ..Default::default()
+            },
+            queue_is_released: Notify::new(),
+            is_shutting_down: AtomicBool::new(false),
+            retry_delay_random: RetryDelayRandom::new(),
+            logger: TestLogger::new(),
+        }
+    }
+
+    async fn get_queued_contents(data: &PublisherWithInternalQueueData) -> Vec<u8> {
+        let read_access = data.queue_to_publish.lock().await;
+        read_access
+            .queue
+            .iter()
+            .map(|itm| itm.message.content[0])
+            .collect()
+    }
+
+    #[test]
+    fn test_headers_are_counted_in_size() {
+        let mut headers = HashMap::new();
+        headers.insert("key".to_string(), "value".to_string());
+
+        let mut queue = QueueToPublish::new();
+        queue.push(QueueToPublishItem {
+            message: MessageToPublish::new_with_headers(vec![0; 10], headers),
+            confirmation: None,
+            spool_seq: None,
+        });
+
+        assert_eq!(18, queue.get_size_in_bytes());
+
+        queue.pop_front();
+
+        assert_eq!(0, queue.get_size_in_bytes());
+    }
+
+    #[tokio::test]
+    async fn test_reject_policy() {
+        let data = create_data(2, QueueOverflowPolicy::Reject);
+
+        data.enqueue(vec![create_item(1), create_item(1)])
+            .await
+            .unwrap();
+
+        let (item, confirmation) = create_numbered_item(3);
+        let result = data.enqueue(vec![item]).await;
+
+        assert!(matches!(result, Err(PublishError::QueueIsFull)));
+        assert!(matches!(confirmation.await, Err(PublishError::QueueIsFull)));
+        assert_eq!(2, data.queue_to_publish.lock().await.queue.len());
+    }
+
+    #[tokio::test]
+    async fn test_drop_newest_policy() {
+        let data = create_data(2, QueueOverflowPolicy::DropNewest);
+
+        let (item_1, _) = create_numbered_item(1);
+        let (item_2, _) = create_numbered_item(2);
+        data.enqueue(vec![item_1, item_2]).await.unwrap();
+
+        let (item_3, confirmation) = create_numbered_item(3);
+        let result = data.enqueue(vec![item_3]).await;
+
+        assert!(matches!(result, Err(PublishError::QueueIsFull)));
+        assert!(matches!(confirmation.await, Err(PublishError::QueueIsFull)));
+        assert_eq!(vec![1, 2], get_queued_contents(&data).await);
+    }
+
+    #[tokio::test]
+    async fn test_drop_oldest_policy() {
+        let data = create_data(2, QueueOverflowPolicy::DropOldest);
+
+        let (item_1, confirmation_1) = create_numbered_item(1);
+        let (item_2, _) = create_numbered_item(2);
+        data.enqueue(vec![item_1, item_2]).await.unwrap();
+
+        let (item_3, _) = create_numbered_item(3);
+        data.enqueue(vec![item_3]).await.unwrap();
+
+        assert!(matches!(
+            confirmation_1.await,
+            Err(PublishError::QueueIsFull)
+        ));
+        assert_eq!(vec![2, 3], get_queued_contents(&data).await);
+    }
+
+    #[tokio::test]
+    async fn test_wait_policy() {
+        let data = Arc::new(create_data(2, QueueOverflowPolicy::Wait));
+
+        data.enqueue(vec![create_numbered_item(1).0, create_numbered_item(2).0])
+            .await
+            .unwrap();
+
+        let enqueue_task = {
+            let data = data.clone();
+            tokio::spawn(async move { data.enqueue(vec![create_numbered_item(3).0]).await })
+        };
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(false, enqueue_task.is_finished());
+
+        let batch = data.get_messages_to_publish().await.unwrap();
+        data.messages_are_published(batch.batch_id).await;
+
+        tokio::time::timeout(Duration::from_secs(1), enqueue_task)
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(vec![3], get_queued_contents(&data).await);
+    }
+
+    #[tokio::test]
+    async fn test_batch_which_can_never_fit_is_rejected() {
+        let data = create_data(2, QueueOverflowPolicy::Wait);
+
+        let result = data
+            .enqueue(vec![create_item(1), create_item(1), create_item(1)])
+            .await;
+
+        assert!(matches!(result, Err(PublishError::QueueIsFull)));
+    }
+
+    #[tokio::test]
+    async fn test_zero_batch_caps_publish_one_message_per_batch() {
+        let mut data = create_data(10, QueueOverflowPolicy::Reject);
+        data.settings.publish_policy.max_batch_messages = Some(0);
+        data.settings.publish_policy.max_batch_size_in_bytes = 0;
+
+        data.enqueue(vec![create_item(1), create_item(1)])
+            .await
+            .unwrap();
+
+        let batch = data.get_messages_to_publish().await.unwrap();
+        assert_eq!(1, batch.messages.len());
+
+        let batch = data.get_messages_to_publish().await.unwrap();
+        assert_eq!(1, batch.messages.len());
+
+        assert_eq!(true, data.get_messages_to_publish().await.is_none());
+    }
+
+    #[test]
+    fn test_remaining_capacity() {
+        let settings = PublisherWithInternalQueueSettings {
+            max_messages: Some(3),
+            max_bytes: Some(100),
+            overflow_policy: QueueOverflowPolicy::Reject,
+            disk_spool: None,
+            publish_policy: Default::default(),
+            interceptors: Default::default(),
+        };
+
+        let mut queue = QueueToPublish::new();
+        queue.push(create_item(40));
+        queue.push(create_item(40));
+
+        let remaining = queue.get_remaining_capacity(&settings);
+        assert_eq!(Some(1), remaining.messages);
+        assert_eq!(Some(20), remaining.bytes);
+
+        assert_eq!(true, queue.has_room_for(&settings, 1, 20));
+        assert_eq!(false, queue.has_room_for(&settings, 1, 21));
+        assert_eq!(false, queue.has_room_for(&settings, 2, 0));
+
+        queue.pop_front();
+
+        let remaining = queue.get_remaining_capacity(&settings);
+        assert_eq!(Some(2), remaining.messages);
+        assert_eq!(Some(60), remaining.bytes);
+    }
+}
@@ -0,0 +1,690 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    fs::{File, OpenOptions},
+    io::{Read, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use crate::{publisher::MessageToPublish, queue_with_intervals::QueueWithIntervals};
+
+const SEGMENT_FILE_EXTENSION: &str = "seg";
+const CHECKPOINT_FILE_NAME: &str = "checkpoint";
+const CHECKPOINT_TMP_FILE_NAME: &str = "checkpoint.tmp";
+const RECORD_HEADER_SIZE: usize = 8;
+
+#[derive(Debug, Clone)]
+pub struct PublisherDiskSpoolSettings {
+    pub path: PathBuf,
+    pub max_segment_size: u64,
+    pub sync_on_write: bool,
+}
+
+impl PublisherDiskSpoolSettings {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            max_segment_size: 64 * 1024 * 1024,
+            sync_on_write: false,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum DiskSpoolError {
+    Io(std::io::Error),
+    Corrupted { file: PathBuf, position: u64 },
+}
+
+impl From<std::io::Error> for DiskSpoolError {
+    fn from(err: std::io::Error) -> Self {
+        DiskSpoolError::Io(err)
+    }
+}
+
+pub struct SpooledMessage {
+    pub seq: u64,
+    pub message: MessageToPublish,
+}
+
+pub struct DiskSpoolReplay {
+    pub messages: Vec<SpooledMessage>,
+    pub corrupted: Vec<DiskSpoolError>,
+}
+
+struct SpoolSegment {
+    file_no: u64,
+    last_seq: Option<u64>,
+}
+
+pub struct PublisherDiskSpool {
+    path: PathBuf,
+    max_segment_size: u64,
+    sync_on_write: bool,
+    segments: VecDeque<SpoolSegment>,
+    current_file: Option<File>,
+    current_segment_size: u64,
+    next_seq: u64,
+    published_up_to: u64,
+    published: QueueWithIntervals,
+    checkpoint_is_dirty: bool,
+}
+
+impl PublisherDiskSpool {
+    pub fn open(
+        settings: &PublisherDiskSpoolSettings,
+        topic_id: &str,
+    ) -> Result<(Self, DiskSpoolReplay), DiskSpoolError> {
+        let path = settings.path.join(topic_id);
+        std::fs::create_dir_all(&path)?;
+
+        let mut corrupted = Vec::new();
+
+        let published_up_to = match read_checkpoint(&path) {
+            Ok(value) => value,
+            Err(err) => {
+                corrupted.push(err);
+                0
+            }
+        };
+
+        let mut segments = VecDeque::new();
+        let mut messages = Vec::new();
+        let mut next_seq = published_up_to;
+
+        for file_no in get_segment_numbers(&path)? {
+            let file_name = get_segment_file_name(&path, file_no);
+            let mut last_seq = None;
+
+            let content = std::fs::read(&file_name)?;
+
+            let mut position = 0;
+            while position < content.len() {
+                match decode_record(&content[position..]) {
+                    Some((seq, message, record_size)) => {
+                        position += record_size;
+                        last_seq = Some(seq);
+
+                        if seq >= next_seq {
+                            next_seq = seq + 1;
+                        }
+
+                        if seq >= published_up_to {
+                            messages.push(SpooledMessage { seq, message });
+                        }
+                    }
+                    None => {
+                        corrupted.push(DiskSpoolError::Corrupted {
+                            file: file_name.clone(),
+                            position: position as u64,
+                        });
+                        break;
+                    }
+                }
+            }
+
+            segments.push_back(SpoolSegment { file_no, last_seq });
+        }
+
+        let mut result = Self {
+            path,
+            max_segment_size: settings.max_segment_size,
+            sync_on_write: settings.sync_on_write,
+            segments,
+            current_file: None,
+            current_segment_size: 0,
+            next_seq,
+            published_up_to,
+            published: QueueWithIntervals::new(),
+            checkpoint_is_dirty: false,
+        };
+
+        let mut expected_seq = published_up_to;
+        for message in messages.iter() {
+            for lost_seq in expected_seq..message.seq {
+                result.mark_as_published(lost_seq);
+            }
+
+            expected_seq = expected_seq.max(message.seq + 1);
+        }
+
+        for lost_seq in expected_seq..next_seq {
+            result.mark_as_published(lost_seq);
+        }
+
+        result.commit()?;
+        result.remove_published_segments()?;
+
+        Ok((
+            result,
+            DiskSpoolReplay {
+                messages,
+                corrupted,
+            },
+        ))
+    }
+
+    pub fn append(&mut self, message: &MessageToPublish) -> Result<u64, DiskSpoolError> {
+        if self.current_file.is_none() || self.current_segment_size >= self.max_segment_size {
+            self.start_new_segment()?;
+        }
+
+        let seq = self.next_seq;
+        let record = encode_record(seq, message);
+
+        let file = self.current_file.as_mut().unwrap();
+
+        if let Err(err) = write_record(file, &record, self.sync_on_write) {
+            if file.set_len(self.current_segment_size).is_err() {
+                self.current_file = None;
+            }
+
+            return Err(err.into());
+        }
+
+        self.next_seq += 1;
+        self.current_segment_size += record.len() as u64;
+        self.segments.back_mut().unwrap().last_seq = Some(seq);
+
+        Ok(seq)
+    }
+
+    pub fn mark_as_published(&mut self, seq: u64) {
+        if seq < self.published_up_to || self.published.has_message(seq as i64) {
+            return;
+        }
+
+        self.published.enqueue(seq as i64);
+
+        while self.published.peek() == Some(self.published_up_to as i64) {
+            self.published.dequeue();
+            self.published_up_to += 1;
+            self.checkpoint_is_dirty = true;
+        }
+    }
+
+    pub fn commit(&mut self) -> Result<(), DiskSpoolError> {
+        if !self.checkpoint_is_dirty {
+            return Ok(());
+        }
+
+        write_checkpoint(&self.path, self.published_up_to)?;
+        self.checkpoint_is_dirty = false;
+
+        self.remove_published_segments()
+    }
+
+    fn start_new_segment(&mut self) -> Result<(), DiskSpoolError> {
+        let file_no = match self.segments.back() {
+            Some(segment) => segment.file_no + 1,
+            None => 0,
+        };
+
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(get_segment_file_name(&self.path, file_no))?;
+
+        self.current_file = Some(file);
+        self.current_segment_size = 0;
+        self.segments.push_back(SpoolSegment {
+            file_no,
+            last_seq: None,
+        });
+
+        Ok(())
+    }
+
+    fn remove_published_segments(&mut self) -> Result<(), DiskSpoolError> {
+        while let Some(segment) = self.segments.front() {
+            let is_published = match segment.last_seq {
+                Some(last_seq) => last_seq < self.published_up_to,
+                None => self.current_file.is_none() || self.segments.len() > 1,
+            };
+
+            if !is_published {
+                break;
+            }
+
+            if self.segments.len() == 1 {
+                self.current_file = None;
+                self.current_segment_size = 0;
+            }
+
+            let segment = self.segments.pop_front().unwrap();
+            std::fs::remove_file(get_segment_file_name(&self.path, segment.file_no))?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Clone)]
+pub struct SharedDiskSpool {
+    spool: Arc<Mutex<PublisherDiskSpool>>,
+}
+
+impl SharedDiskSpool {
+    pub fn new(spool: PublisherDiskSpool) -> Self {
+        Self {
+            spool: Arc::new(Mutex::new(spool)),
+        }
+    }
+
+    pub async fn run<TResult: Send + 'static>(
+        &self,
+        action: impl FnOnce(&mut PublisherDiskSpool) -> TResult + Send + 'static,
+    ) -> Result<TResult, DiskSpoolError> {
+        let spool = self.spool.clone();
+
+        tokio::task::spawn_blocking(move || {
+            let mut spool = spool.lock().unwrap();
+            action(&mut spool)
+        })
+        .await
+        .map_err(|err| {
+            DiskSpoolError::Io(std::io::Error::other(format!(
+                "Disk spool task failed: {}",
+                err
+            )))
+        })
+    }
+}
+
+fn write_record(file: &mut File, record: &[u8], sync_on_write: bool) -> std::io::Result<()> {
+    file.write_all(record)?;
+
+    if sync_on_write {
+        file.sync_data()?;
+    }
+
+    Ok(())
+}
+
+fn get_segment_file_name(path: &Path, file_no: u64) -> PathBuf {
+    path.join(format!("{:020}.{}", file_no, SEGMENT_FILE_EXTENSION))
+}
+
+fn get_segment_numbers(path: &Path) -> Result<Vec<u64>, DiskSpoolError> {
+    let mut result = Vec::new();
+
+    for entry in std::fs::read_dir(path)? {
+        let file_path = entry?.path();
+
+        if file_path.extension().and_then(|itm| itm.to_str()) != Some(SEGMENT_FILE_EXTENSION) {
+            continue;
+        }
+
+        let file_no = file_path
+            .file_stem()
+            .and_then(|itm| itm.to_str())
+            .and_then(|itm| itm.parse::<u64>().ok());
+
+        if let Some(file_no) = file_no {
+            result.push(file_no);
+        }
+    }
+
+    result.sort();
+    Ok(result)
+}
+
+fn read_checkpoint(path: &Path) -> Result<u64, DiskSpoolError> {
+    let file_name = path.join(CHECKPOINT_FILE_NAME);
+
+    let mut file = match File::open(&file_name) {
+        Ok(file) => file,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(err) => return Err(err.into()),
+    };
+
+    let mut content = Vec::new();
+    file.read_to_end(&mut content)?;
+
+    if content.len() != 12 || crc32(&content[..8]).to_le_bytes() != content[8..] {
+        return Err(DiskSpoolError::Corrupted {
+            file: file_name,
+            position: 0,
+        });
+    }
+
+    Ok(u64::from_le_bytes(content[..8].try_into().unwrap()))
+}
+
+fn write_checkpoint(path: &Path, published_up_to: u64) -> Result<(), DiskSpoolError> {
+    let mut content = Vec::with_capacity(12);
+    content.extend_from_slice(&published_up_to.to_le_bytes());
+    content.extend_from_slice(&crc32(&content).to_le_bytes());
+
+    let tmp_file_name = path.join(CHECKPOINT_TMP_FILE_NAME);
+    std::fs::write(&tmp_file_name, &content)?;
+    std::fs::rename(tmp_file_name, path.join(CHECKPOINT_FILE_NAME))?;
+
+    Ok(())
+}
+
+fn encode_record(seq: u64, message: &MessageToPublish) -> Vec<u8> {
+    let mut payload = Vec::with_capacity(message.content.len() + 32);
+    payload.extend_from_slice(&seq.to_le_bytes());
+
+    match message.headers.as_ref() {
+        Some(headers) => {
+            payload.push(1);
+            payload.extend_from_slice(&(headers.len() as u32).to_le_bytes());
+
+            for (key, value) in headers {
+                write_bytes(&mut payload, key.as_bytes());
+                write_bytes(&mut payload, value.as_bytes());
+            }
+        }
+        None => payload.push(0),
+    }
+
+    write_bytes(&mut payload, &message.content);
+
+    let mut result = Vec::with_capacity(payload.len() + RECORD_HEADER_SIZE);
+    result.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    result.extend_from_slice(&crc32(&payload).to_le_bytes());
+    result.extend_from_slice(&payload);
+    result
+}
+
+fn decode_record(src: &[u8]) -> Option<(u64, MessageToPublish, usize)> {
+    if src.len() < RECORD_HEADER_SIZE {
+        return None;
+    }
+
+    let payload_len = u32::from_le_bytes(src[0..4].try_into().ok()?) as usize;
+    let crc = u32::from_le_bytes(src[4..8].try_into().ok()?);
+
+    let payload = src.get(RECORD_HEADER_SIZE..RECORD_HEADER_SIZE + payload_len)?;
+
+    if crc32(payload) != crc {
+        return None;
+    }
+
+    let mut reader = RecordReader { src: payload };
+
+    let seq = u64::from_le_bytes(reader.read(8)?.try_into().ok()?);
+
+    let headers = match reader.read(1)?[0] {
+        0 => None,
+        _ => {
+            let headers_amount = reader.read_u32()?;
+            let mut headers = HashMap::new();
+
+            for _ in 0..headers_amount {
+                let key = String::from_utf8(reader.read_bytes()?.to_vec()).ok()?;
+                let value = String::from_utf8(reader.read_bytes()?.to_vec()).ok()?;
+                headers.insert(key, value);
+            }
+
+            Some(headers)
+        }
+    };
+
+    let content = reader.read_bytes()?.to_vec();
+
+    Some((
+        seq,
+        MessageToPublish { headers, content },
+        RECORD_HEADER_SIZE + payload_len,
+    ))
+}
+
+fn write_bytes(dest: &mut Vec<u8>, src: &[u8]) {
+    dest.extend_from_slice(&(src.len() as u32).to_le_bytes());
+    dest.extend_from_slice(src);
+}
+
+struct RecordReader<'s> {
+    src: &'s [u8],
+}
+
+impl<'s> RecordReader<'s> {
+    fn read(&mut self, size: usize) -> Option<&'s [u8]> {
+        if self.src.len() < size {
+            return None;
+        }
+
+        let (result, rest) = self.src.split_at(size);
+        self.src = rest;
+        Some(result)
+    }
+
+    fn read_u32(&mut self) -> Option<u32> {
+        Some(u32::from_le_bytes(self.read(4)?.try_into().ok()?))
+    }
+
+    fn read_bytes(&mut self) -> Option<&'s [u8]> {
+        let size = self.read_u32()? as usize;
+        self.read(size)
+    }
+}
+
+const CRC32_TABLE: [u32; 256] = build_crc32_table();
+
+const fn build_crc32_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+
+        while bit < 8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xEDB88320
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+
+        table[i] = crc;
+        i += 1;
+    }
+
+    table
+}
+
+fn crc32(src: &[u8]) -> u32 {
+    let mut crc = 0xFFFFFFFFu32;
+
+    for b in src {
+        crc = CRC32_TABLE[((crc ^ *b as u32) & 0xFF) as usize] ^ (crc >> 8);
+    }
+
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_settings(test_name: &str) -> PublisherDiskSpoolSettings {
+        let path =
+            std::env::temp_dir().join(format!("my-sb-spool-{}-{}", test_name, std::process::id()));
+
+        let _ = std::fs::remove_dir_all(&path);
+        PublisherDiskSpoolSettings::new(path)
+    }
+
+    fn create_message(content: &[u8]) -> MessageToPublish {
+        let mut headers = HashMap::new();
+        headers.insert("key".to_string(), "value".to_string());
+        MessageToPublish::new_with_headers(content.to_vec(), headers)
+    }
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(0xCBF43926, crc32(b"123456789"));
+    }
+
+    #[test]
+    fn test_not_published_messages_are_replayed() {
+        let settings = create_settings("replay");
+
+        {
+            let (mut spool, replay) = PublisherDiskSpool::open(&settings, "topic").unwrap();
+            assert_eq!(0, replay.messages.len());
+
+            let seq = spool.append(&create_message(&[1])).unwrap();
+            spool.append(&create_message(&[2])).unwrap();
+            spool.append(&MessageToPublish::new(vec![3])).unwrap();
+
+            spool.mark_as_published(seq);
+            spool.commit().unwrap();
+        }
+
+        let (_, replay) = PublisherDiskSpool::open(&settings, "topic").unwrap();
+
+        assert_eq!(2, replay.messages.len());
+        assert_eq!(0, replay.corrupted.len());
+
+        assert_eq!(vec![2], replay.messages[0].message.content);
+        assert_eq!(
+            "value",
+            replay.messages[0].message.headers.as_ref().unwrap()["key"]
+        );
+        assert_eq!(vec![3], replay.messages[1].message.content);
+        assert_eq!(true, replay.messages[1].message.headers.is_none());
+
+        std::fs::remove_dir_all(&settings.path).unwrap();
+    }
+
+    #[test]
+    fn test_published_segments_are_removed() {
+        let mut settings = create_settings("rotation");
+        settings.max_segment_size = 1;
+
+        let (mut spool, _) = PublisherDiskSpool::open(&settings, "topic").unwrap();
+
+        for i in 0..3 {
+            spool.append(&create_message(&[i])).unwrap();
+        }
+
+        let path = settings.path.join("topic");
+        assert_eq!(3, get_segment_numbers(&path).unwrap().len());
+
+        spool.mark_as_published(1);
+        spool.commit().unwrap();
+        assert_eq!(3, get_segment_numbers(&path).unwrap().len());
+
+        spool.mark_as_published(0);
+        spool.commit().unwrap();
+        assert_eq!(1, get_segment_numbers(&path).unwrap().len());
+
+        spool.mark_as_published(2);
+        spool.commit().unwrap();
+        assert_eq!(0, get_segment_numbers(&path).unwrap().len());
+
+        spool.append(&create_message(&[4])).unwrap();
+        drop(spool);
+
+        let (_, replay) = PublisherDiskSpool::open(&settings, "topic").unwrap();
+        assert_eq!(1, replay.messages.len());
+        assert_eq!(3, replay.messages[0].seq);
+
+        std::fs::remove_dir_all(&settings.path).unwrap();
+    }
+
+    #[test]
+    fn test_failed_write_does_not_break_next_records() {
+        let settings = create_settings("failed-write");
+        let path = settings.path.join("topic");
+
+        {
+            let (mut spool, _) = PublisherDiskSpool::open(&settings, "topic").unwrap();
+            spool.append(&create_message(&[1])).unwrap();
+
+            spool.current_file = Some(File::open(get_segment_file_name(&path, 0)).unwrap());
+            assert_eq!(true, spool.append(&create_message(&[2])).is_err());
+
+            spool.append(&create_message(&[3])).unwrap();
+        }
+
+        assert_eq!(2, get_segment_numbers(&path).unwrap().len());
+
+        let (_, replay) = PublisherDiskSpool::open(&settings, "topic").unwrap();
+
+        assert_eq!(0, replay.corrupted.len());
+        assert_eq!(2, replay.messages.len());
+        assert_eq!(vec![1], replay.messages[0].message.content);
+        assert_eq!(vec![3], replay.messages[1].message.content);
+
+        std::fs::remove_dir_all(&settings.path).unwrap();
+    }
+
+    #[test]
+    fn test_corrupted_tail_is_detected() {
+        let settings = create_settings("corrupted");
+
+        {
+            let (mut spool, _) = PublisherDiskSpool::open(&settings, "topic").unwrap();
+            spool.append(&create_message(&[1])).unwrap();
+            spool.append(&create_message(&[2])).unwrap();
+        }
+
+        let file_name = get_segment_file_name(&settings.path.join("topic"), 0);
+        let mut content = std::fs::read(&file_name).unwrap();
+        let last_index = content.len() - 1;
+        content[last_index] ^= 0xFF;
+        std::fs::write(&file_name, content).unwrap();
+
+        let (_, replay) = PublisherDiskSpool::open(&settings, "topic").unwrap();
+
+        assert_eq!(1, replay.messages.len());
+        assert_eq!(vec![1], replay.messages[0].message.content);
+        assert_eq!(1, replay.corrupted.len());
+
+        std::fs::remove_dir_all(&settings.path).unwrap();
+    }
+
+    #[test]
+    fn test_records_lost_after_corruption_do_not_block_checkpoint() {
+        let mut settings = create_settings("corrupted-middle");
+        settings.max_segment_size = 60;
+
+        let path = settings.path.join("topic");
+
+        {
+            let (mut spool, _) = PublisherDiskSpool::open(&settings, "topic").unwrap();
+
+            for i in 0..4 {
+                spool.append(&MessageToPublish::new(vec![i])).unwrap();
+            }
+        }
+
+        assert_eq!(2, get_segment_numbers(&path).unwrap().len());
+
+        let file_name = get_segment_file_name(&path, 0);
+        let mut content = std::fs::read(&file_name).unwrap();
+        let second_record_index = content.len() / 3 + RECORD_HEADER_SIZE;
+        content[second_record_index] ^= 0xFF;
+        std::fs::write(&file_name, content).unwrap();
+
+        let (mut spool, replay) = PublisherDiskSpool::open(&settings, "topic").unwrap();
+
+        assert_eq!(1, replay.corrupted.len());
+        assert_eq!(2, replay.messages.len());
+        assert_eq!(0, replay.messages[0].seq);
+        assert_eq!(3, replay.messages[1].seq);
+
+        for message in replay.messages.iter() {
+            spool.mark_as_published(message.seq);
+        }
+        spool.commit().unwrap();
+
+        assert_eq!(4, read_checkpoint(&path).unwrap());
+        assert_eq!(0, get_segment_numbers(&path).unwrap().len());
+
+        drop(spool);
+
+        let (_, replay) = PublisherDiskSpool::open(&settings, "topic").unwrap();
+        assert_eq!(0, replay.messages.len());
+        assert_eq!(0, replay.corrupted.len());
+
+        std::fs::remove_dir_all(&settings.path).unwrap();
+    }
+}
@@ -1,4 +1,12 @@
 mod data;
+mod disk_spool;
+mod publish_confirmation;
+mod publish_policy;
 mod publisher_with_internal_queue;
+mod settings;
 pub use data::*;
+pub use disk_spool::*;
+pub use publish_confirmation::*;
+pub use publish_policy::*;
 pub use publisher_with_internal_queue::*;
+pub use settings::*;
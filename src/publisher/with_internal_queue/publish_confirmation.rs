@@ -0,0 +1,94 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    task::{Context, Poll},
+};
+
+use tokio::sync::oneshot;
+
+use crate::PublishError;
+
+pub struct PublishConfirmationState {
+    messages_to_publish: AtomicUsize,
+    sender: Mutex<Option<oneshot::Sender<Result<(), PublishError>>>>,
+}
+
+impl PublishConfirmationState {
+    pub fn new(messages_amount: usize) -> (Self, PublishConfirmation) {
+        let (sender, receiver) = oneshot::channel();
+
+        let result = Self {
+            messages_to_publish: AtomicUsize::new(messages_amount),
+            sender: Mutex::new(Some(sender)),
+        };
+
+        if messages_amount == 0 {
+            result.set_result(Ok(()));
+        }
+
+        (result, PublishConfirmation { receiver })
+    }
+
+    pub fn message_is_published(&self) {
+        if self.messages_to_publish.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.set_result(Ok(()));
+        }
+    }
+
+    pub fn set_error(&self, err: PublishError) {
+        self.set_result(Err(err));
+    }
+
+    fn set_result(&self, result: Result<(), PublishError>) {
+        if let Some(sender) = self.sender.lock().unwrap().take() {
+            let _ = sender.send(result);
+        }
+    }
+}
+
+pub struct PublishConfirmation {
+    receiver: oneshot::Receiver<Result<(), PublishError>>,
+}
+
+impl Future for PublishConfirmation {
+    type Output = Result<(), PublishError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match Pin::new(&mut self.receiver).poll(cx) {
+            Poll::Ready(Ok(result)) => Poll::Ready(result),
+            Poll::Ready(Err(_)) => Poll::Ready(Err(PublishError::Other(
+                "Publisher was dropped before the message was published".to_string(),
+            ))),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_confirmation_is_resolved_when_all_messages_are_published() {
+        let (state, mut confirmation) = PublishConfirmationState::new(2);
+
+        state.message_is_published();
+
+        let result = tokio::time::timeout(std::time::Duration::from_millis(10), &mut confirmation);
+        assert_eq!(true, result.await.is_err());
+
+        state.message_is_published();
+
+        assert_eq!(true, confirmation.await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_empty_chunk_is_confirmed_immediately() {
+        let (_state, confirmation) = PublishConfirmationState::new(0);
+        assert_eq!(true, confirmation.await.is_ok());
+    }
+}
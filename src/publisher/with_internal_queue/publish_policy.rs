@@ -0,0 +1,284 @@
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+#[derive(Debug, Clone)]
+pub struct PublishPolicy {
+    pub max_batch_size_in_bytes: usize,
+    pub max_batch_messages: Option<usize>,
+    pub linger: Option<Duration>,
+    pub do_retry: bool,
+    pub retry_delay: Duration,
+    pub max_retry_delay: Duration,
+    pub retry_delay_multiplier: f64,
+    pub retry_jitter: f64,
+    pub max_in_flight_batches: usize,
+    pub keep_order: bool,
+}
+
+impl PublishPolicy {
+    pub fn is_batch_full(&self, messages_amount: usize, size_in_bytes: usize) -> bool {
+        if messages_amount == 0 {
+            return false;
+        }
+
+        if size_in_bytes >= self.max_batch_size_in_bytes {
+            return true;
+        }
+
+        if let Some(max_batch_messages) = self.max_batch_messages {
+            if messages_amount >= max_batch_messages {
+                return true;
+            }
+        }
+
+        false
+    }
+
+    pub fn get_max_in_flight_batches(&self) -> usize {
+        if self.keep_order {
+            return 1;
+        }
+
+        self.max_in_flight_batches.max(1)
+    }
+
+    pub fn get_in_flight_batches_warning(&self) -> Option<&'static str> {
+        if self.max_in_flight_batches == 0 {
+            return Some("max_in_flight_batches is 0. Batches are published one by one");
+        }
+
+        if self.keep_order && self.max_in_flight_batches > 1 {
+            return Some("keep_order is set. max_in_flight_batches is ignored and batches are published one by one");
+        }
+
+        None
+    }
+
+    pub fn get_retry_delay(&self, attempt_no: u32, random: &RetryDelayRandom) -> Duration {
+        let max_delay = self.max_retry_delay.as_secs_f64();
+
+        let mut delay = self.retry_delay.as_secs_f64()
+            * self
+                .retry_delay_multiplier
+                .powi(attempt_no.min(i32::MAX as u32) as i32);
+
+        if delay > max_delay {
+            delay = max_delay;
+        }
+
+        if self.retry_jitter > 0.0 {
+            let jitter = delay * self.retry_jitter;
+            delay = delay - jitter + 2.0 * jitter * random.next_f64();
+        }
+
+        if delay > max_delay {
+            delay = max_delay;
+        }
+
+        Duration::from_secs_f64(delay.max(0.0))
+    }
+}
+
+impl Default for PublishPolicy {
+    fn default() -> Self {
+        Self {
+            max_batch_size_in_bytes: 4_000_000,
+            max_batch_messages: None,
+            linger: None,
+            do_retry: true,
+            retry_delay: Duration::from_secs(3),
+            max_retry_delay: Duration::from_secs(3),
+            retry_delay_multiplier: 1.0,
+            retry_jitter: 0.0,
+            max_in_flight_batches: 1,
+            keep_order: false,
+        }
+    }
+}
+
+pub struct RetryDelayRandom {
+    state: AtomicU64,
+}
+
+impl RetryDelayRandom {
+    pub fn new() -> Self {
+        Self::new_with_seed(RandomState::new().build_hasher().finish())
+    }
+
+    pub fn new_with_seed(seed: u64) -> Self {
+        Self {
+            state: AtomicU64::new(seed.max(1)),
+        }
+    }
+
+    pub fn next_f64(&self) -> f64 {
+        let mut value = self.state.load(Ordering::Relaxed);
+
+        loop {
+            let next = xorshift(value);
+
+            match self.state.compare_exchange_weak(
+                value,
+                next,
+                Ordering::Relaxed,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => return (next >> 11) as f64 / (1u64 << 53) as f64,
+                Err(current) => value = current,
+            }
+        }
+    }
+}
+
+fn xorshift(mut value: u64) -> u64 {
+    value ^= value << 13;
+    value ^= value >> 7;
+    value ^= value << 17;
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_policy_keeps_fixed_delay() {
+        let policy = PublishPolicy::default();
+        let random = RetryDelayRandom::new();
+
+        assert_eq!(Duration::from_secs(3), policy.get_retry_delay(0, &random));
+        assert_eq!(Duration::from_secs(3), policy.get_retry_delay(10, &random));
+    }
+
+    #[test]
+    fn test_exponential_backoff_grows() {
+        let policy = PublishPolicy {
+            retry_delay: Duration::from_millis(100),
+            max_retry_delay: Duration::from_secs(10),
+            retry_delay_multiplier: 2.0,
+            ..Default::default()
+        };
+        let random = RetryDelayRandom::new();
+
+        assert_eq!(
+            Duration::from_millis(100),
+            policy.get_retry_delay(0, &random)
+        );
+        assert_eq!(
+            Duration::from_millis(200),
+            policy.get_retry_delay(1, &random)
+        );
+        assert_eq!(
+            Duration::from_millis(400),
+            policy.get_retry_delay(2, &random)
+        );
+        assert_eq!(
+            Duration::from_millis(800),
+            policy.get_retry_delay(3, &random)
+        );
+    }
+
+    #[test]
+    fn test_exponential_backoff_is_capped() {
+        let policy = PublishPolicy {
+            retry_delay: Duration::from_millis(100),
+            max_retry_delay: Duration::from_secs(1),
+            retry_delay_multiplier: 2.0,
+            ..Default::default()
+        };
+        let random = RetryDelayRandom::new();
+
+        assert_eq!(Duration::from_secs(1), policy.get_retry_delay(4, &random));
+        assert_eq!(Duration::from_secs(1), policy.get_retry_delay(10, &random));
+        assert_eq!(
+            Duration::from_secs(1),
+            policy.get_retry_delay(u32::MAX, &random)
+        );
+    }
+
+    #[test]
+    fn test_jitter_stays_in_range() {
+        let policy = PublishPolicy {
+            retry_delay: Duration::from_millis(100),
+            max_retry_delay: Duration::from_secs(1),
+            retry_jitter: 0.5,
+            ..Default::default()
+        };
+        let random = RetryDelayRandom::new();
+
+        for _ in 0..100 {
+            let delay = policy.get_retry_delay(0, &random);
+            assert_eq!(true, delay >= Duration::from_millis(50));
+            assert_eq!(true, delay <= Duration::from_millis(150));
+        }
+    }
+
+    #[test]
+    fn test_jitter_does_not_exceed_max_delay() {
+        let policy = PublishPolicy {
+            retry_delay: Duration::from_millis(100),
+            max_retry_delay: Duration::from_millis(300),
+            retry_delay_multiplier: 2.0,
+            retry_jitter: 0.5,
+            ..Default::default()
+        };
+        let random = RetryDelayRandom::new();
+
+        for _ in 0..100 {
+            let delay = policy.get_retry_delay(5, &random);
+            assert_eq!(true, delay >= Duration::from_millis(150));
+            assert_eq!(true, delay <= Duration::from_millis(300));
+        }
+    }
+
+    #[test]
+    fn test_seeded_random_is_deterministic() {
+        let random_1 = RetryDelayRandom::new_with_seed(42);
+        let random_2 = RetryDelayRandom::new_with_seed(42);
+
+        for _ in 0..100 {
+            let value = random_1.next_f64();
+            assert_eq!(value, random_2.next_f64());
+            assert_eq!(true, (0.0..1.0).contains(&value));
+        }
+
+        let zero_seed = RetryDelayRandom::new_with_seed(0);
+        assert_ne!(zero_seed.next_f64(), zero_seed.next_f64());
+    }
+
+    #[test]
+    fn test_max_in_flight_batches() {
+        let mut policy = PublishPolicy {
+            max_in_flight_batches: 4,
+            ..Default::default()
+        };
+
+        assert_eq!(4, policy.get_max_in_flight_batches());
+        assert_eq!(true, policy.get_in_flight_batches_warning().is_none());
+
+        policy.keep_order = true;
+        assert_eq!(1, policy.get_max_in_flight_batches());
+        assert_eq!(true, policy.get_in_flight_batches_warning().is_some());
+
+        policy.keep_order = false;
+        policy.max_in_flight_batches = 0;
+        assert_eq!(1, policy.get_max_in_flight_batches());
+        assert_eq!(true, policy.get_in_flight_batches_warning().is_some());
+    }
+
+    #[test]
+    fn test_zero_caps_still_fill_one_message() {
+        let policy = PublishPolicy {
+            max_batch_size_in_bytes: 0,
+            max_batch_messages: Some(0),
+            ..Default::default()
+        };
+
+        assert_eq!(false, policy.is_batch_full(0, 0));
+        assert_eq!(true, policy.is_batch_full(1, 10));
+    }
+}
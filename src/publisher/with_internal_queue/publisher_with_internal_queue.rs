@@ -1,23 +1,38 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
 
 #[cfg(feature = "with-telemetry")]
 use my_telemetry::MyTelemetryContext;
 
-use tokio::sync::{
-    mpsc::{UnboundedReceiver, UnboundedSender},
-    Mutex,
+use tokio::{
+    sync::{
+        mpsc::{UnboundedReceiver, UnboundedSender},
+        watch, Mutex, Notify,
+    },
+    task::{JoinHandle, JoinSet},
 };
 
 use crate::{MyServiceBusPublisherClient, PublishError};
 
 use super::{
-    super::{MessageToPublish, MySbMessageSerializer},
-    PublisherWithInternalQueueData, QueueToPublish,
+    super::{MessageToPublish, MySbMessageSerializer, PublishContext},
+    BatchToPublish, DiskSpoolError, PublishConfirmation, PublishConfirmationState,
+    PublisherDiskSpool, PublisherQueueRemainingCapacity, PublisherWithInternalQueueData,
+    PublisherWithInternalQueueSettings, QueueToPublish, QueueToPublishItem, RetryDelayRandom,
+    SharedDiskSpool,
 };
 
 pub struct PublisherWithInternalQueue<TMessageModel: MySbMessageSerializer> {
     data: Arc<PublisherWithInternalQueueData>,
     event_sender: UnboundedSender<()>,
+    stop_sender: watch::Sender<bool>,
+    events_publisher_task: std::sync::Mutex<Option<JoinHandle<()>>>,
     pub item: Option<TMessageModel>,
 }
 
@@ -26,60 +41,179 @@ impl<TMessageModel: MySbMessageSerializer> PublisherWithInternalQueue<TMessageMo
         topic_id: String,
         client: Arc<dyn MyServiceBusPublisherClient + Send + Sync + 'static>,
         logger: Arc<dyn rust_extensions::Logger + Send + Sync + 'static>,
+    ) -> Self {
+        Self::create(
+            topic_id,
+            client,
+            logger,
+            Default::default(),
+            QueueToPublish::new(),
+        )
+    }
+
+    pub fn new_with_settings(
+        topic_id: String,
+        client: Arc<dyn MyServiceBusPublisherClient + Send + Sync + 'static>,
+        logger: Arc<dyn rust_extensions::Logger + Send + Sync + 'static>,
+        settings: PublisherWithInternalQueueSettings,
+    ) -> Result<Self, DiskSpoolError> {
+        let mut queue_to_publish = QueueToPublish::new();
+
+        if let Some(disk_spool_settings) = settings.disk_spool.as_ref() {
+            let (spool, replay) = PublisherDiskSpool::open(disk_spool_settings, &topic_id)?;
+
+            for err in replay.corrupted {
+                let mut ctx = HashMap::new();
+                ctx.insert("topicId".to_string(), topic_id.to_string());
+                logger.write_error(
+                    "PublisherWithInternalQueue::new".to_string(),
+                    format!("Disk spool is corrupted. Err: {:?}", err),
+                    Some(ctx),
+                );
+            }
+
+            for spooled in replay.messages {
+                queue_to_publish.push(QueueToPublishItem {
+                    message: spooled.message,
+                    confirmation: None,
+                    spool_seq: Some(spooled.seq),
+                });
+            }
+
+            queue_to_publish.spool = Some(SharedDiskSpool::new(spool));
+        }
+
+        Ok(Self::create(
+            topic_id,
+            client,
+            logger,
+            settings,
+            queue_to_publish,
+        ))
+    }
+
+    fn create(
+        topic_id: String,
+        client: Arc<dyn MyServiceBusPublisherClient + Send + Sync + 'static>,
+        logger: Arc<dyn rust_extensions::Logger + Send + Sync + 'static>,
+        settings: PublisherWithInternalQueueSettings,
+        queue_to_publish: QueueToPublish,
     ) -> Self {
         let (event_sender, event_receiver) = tokio::sync::mpsc::unbounded_channel();
+        let (stop_sender, stop_receiver) = watch::channel(false);
+
+        let has_messages_to_publish = !queue_to_publish.queue.is_empty();
+
+        if let Some(warning) = settings.publish_policy.get_in_flight_batches_warning() {
+            let mut ctx = HashMap::new();
+            ctx.insert("topicId".to_string(), topic_id.to_string());
+            logger.write_warning(
+                "PublisherWithInternalQueue::new".to_string(),
+                warning.to_string(),
+                Some(ctx),
+            );
+        }
 
         let data = PublisherWithInternalQueueData {
             client,
             topic_id,
-            queue_to_publish: Mutex::new(QueueToPublish::new()),
+            queue_to_publish: Mutex::new(queue_to_publish),
+            settings,
+            queue_is_released: Notify::new(),
+            is_shutting_down: AtomicBool::new(false),
+            retry_delay_random: RetryDelayRandom::new(),
             logger,
         };
 
         let result = Self {
             event_sender,
+            stop_sender,
             data: Arc::new(data),
+            events_publisher_task: std::sync::Mutex::new(None),
             item: None,
         };
 
+        if has_messages_to_publish {
+            let _ = result.event_sender.send(());
+        }
+
         let data = result.data.clone();
-        tokio::spawn(events_publisher(data, event_receiver));
+        let events_publisher_task =
+            tokio::spawn(events_publisher(data, event_receiver, stop_receiver));
+        *result.events_publisher_task.lock().unwrap() = Some(events_publisher_task);
 
         result
     }
 
-    pub async fn publish_and_forget(
+    fn serialize_message(
         &self,
-        message: TMessageModel,
+        process: &str,
+        message: &TMessageModel,
         #[cfg(feature = "with-telemetry")] telemetry_context: Option<&MyTelemetryContext>,
-    ) -> Result<(), PublishError> {
-        let result = message.serialize(None);
+    ) -> Result<MessageToPublish, PublishError> {
+        let (content, headers) = match message.serialize(None) {
+            Ok(result) => result,
+            Err(err) => {
+                let mut ctx = HashMap::new();
+                ctx.insert("topicId".to_string(), self.data.topic_id.to_string());
+                self.data
+                    .logger
+                    .write_fatal_error(process.to_string(), err.clone(), Some(ctx));
 
-        if let Err(err) = result {
-            return Err(PublishError::SerializationError(err));
-        }
+                return Err(PublishError::SerializationError(err));
+            }
+        };
 
-        #[cfg(not(feature = "with-telemetry"))]
-        let (content, headers) = result.unwrap();
+        let mut result = MessageToPublish { headers, content };
 
-        #[cfg(feature = "with-telemetry")]
-        let (content, mut headers) = result.unwrap();
+        let publish_ctx = PublishContext {
+            topic_id: &self.data.topic_id,
+            #[cfg(feature = "with-telemetry")]
+            telemetry_context,
+        };
 
-        #[cfg(feature = "with-telemetry")]
-        if let Some(my_telemetry) = telemetry_context.as_ref() {
-            super::super::my_telemetry::apply_publish_telemetry(&mut headers, my_telemetry)
+        if let Err(err) = self
+            .data
+            .settings
+            .interceptors
+            .before_publish(&publish_ctx, &mut result)
+        {
+            let mut ctx = HashMap::new();
+            ctx.insert("topicId".to_string(), self.data.topic_id.to_string());
+            self.data.logger.write_error(
+                process.to_string(),
+                format!("Message is rejected by interceptor. Error: {:?}", err),
+                Some(ctx),
+            );
+
+            return Err(err);
         }
 
-        let mut write_access = self.data.queue_to_publish.lock().await;
-        write_access
-            .queue
-            .push_back(MessageToPublish { headers, content });
+        Ok(result)
+    }
+
+    async fn enqueue(
+        &self,
+        messages: Vec<MessageToPublish>,
+        confirmation: Option<Arc<PublishConfirmationState>>,
+        process: &str,
+    ) -> Result<(), PublishError> {
+        let items = messages
+            .into_iter()
+            .map(|message| QueueToPublishItem {
+                message,
+                confirmation: confirmation.clone(),
+                spool_seq: None,
+            })
+            .collect();
+
+        self.data.enqueue(items).await?;
 
         if let Err(err) = self.event_sender.send(()) {
             let mut ctx = HashMap::new();
             ctx.insert("topicId".to_string(), self.data.topic_id.to_string());
             self.data.logger.write_error(
-                "publish_and_forget".to_string(),
+                process.to_string(),
                 format!("Can not publish message. Err: {}", err),
                 Some(ctx),
             )
@@ -88,6 +222,22 @@ impl<TMessageModel: MySbMessageSerializer> PublisherWithInternalQueue<TMessageMo
         Ok(())
     }
 
+    pub async fn publish_and_forget(
+        &self,
+        message: TMessageModel,
+        #[cfg(feature = "with-telemetry")] telemetry_context: Option<&MyTelemetryContext>,
+    ) -> Result<(), PublishError> {
+        let message = self.serialize_message(
+            "publish_and_forget",
+            &message,
+            #[cfg(feature = "with-telemetry")]
+            telemetry_context,
+        )?;
+
+        self.enqueue(vec![message], None, "publish_and_forget")
+            .await
+    }
+
     pub async fn publish_chunk_and_forget(
         &self,
         messages: Vec<TMessageModel>,
@@ -95,71 +245,557 @@ impl<TMessageModel: MySbMessageSerializer> PublisherWithInternalQueue<TMessageMo
     ) -> Result<(), PublishError> {
         let mut to_publish = Vec::with_capacity(messages.len());
 
-        for message in messages {
-            let result = message.serialize(None);
-
-            if let Err(err) = result {
-                return Err(PublishError::SerializationError(err));
-            }
+        for message in &messages {
+            to_publish.push(self.serialize_message(
+                "publish_chunk_and_forget",
+                message,
+                #[cfg(feature = "with-telemetry")]
+                telemetry_context,
+            )?);
+        }
 
-            #[cfg(not(feature = "with-telemetry"))]
-            let (content, headers) = result.unwrap();
+        self.enqueue(to_publish, None, "publish_chunk_and_forget")
+            .await
+    }
 
+    pub async fn publish_with_confirmation(
+        &self,
+        message: TMessageModel,
+        #[cfg(feature = "with-telemetry")] telemetry_context: Option<&MyTelemetryContext>,
+    ) -> Result<PublishConfirmation, PublishError> {
+        let message = self.serialize_message(
+            "publish_with_confirmation",
+            &message,
             #[cfg(feature = "with-telemetry")]
-            let (content, mut headers) = result.unwrap();
+            telemetry_context,
+        )?;
 
-            #[cfg(feature = "with-telemetry")]
-            if let Some(my_telemetry) = telemetry_context.as_ref() {
-                super::super::my_telemetry::apply_publish_telemetry(&mut headers, my_telemetry)
-            }
+        let (confirmation_state, confirmation) = PublishConfirmationState::new(1);
+
+        self.enqueue(
+            vec![message],
+            Some(Arc::new(confirmation_state)),
+            "publish_with_confirmation",
+        )
+        .await?;
+
+        Ok(confirmation)
+    }
+
+    pub async fn publish_chunk_with_confirmation(
+        &self,
+        messages: Vec<TMessageModel>,
+        #[cfg(feature = "with-telemetry")] telemetry_context: Option<&MyTelemetryContext>,
+    ) -> Result<PublishConfirmation, PublishError> {
+        let mut to_publish = Vec::with_capacity(messages.len());
+
+        for message in &messages {
+            to_publish.push(self.serialize_message(
+                "publish_chunk_with_confirmation",
+                message,
+                #[cfg(feature = "with-telemetry")]
+                telemetry_context,
+            )?);
+        }
+
+        let (confirmation_state, confirmation) = PublishConfirmationState::new(to_publish.len());
+
+        self.enqueue(
+            to_publish,
+            Some(Arc::new(confirmation_state)),
+            "publish_chunk_with_confirmation",
+        )
+        .await?;
+
+        Ok(confirmation)
+    }
+
+    pub async fn get_queue_size(&self) -> usize {
+        let read_access = self.data.queue_to_publish.lock().await;
+        read_access.queue.len() + read_access.being_published
+    }
+
+    pub async fn get_remaining_capacity(&self) -> PublisherQueueRemainingCapacity {
+        let read_access = self.data.queue_to_publish.lock().await;
+        read_access.get_remaining_capacity(&self.data.settings)
+    }
+
+    pub async fn flush(&self, timeout: Duration) -> bool {
+        tokio::time::timeout(timeout, self.data.wait_until_everything_is_published())
+            .await
+            .is_ok()
+    }
+
+    pub async fn shutdown(&self, timeout: Duration) -> usize {
+        let deadline = tokio::time::Instant::now() + timeout;
 
-            let msg_to_publish = MessageToPublish { headers, content };
-            to_publish.push(msg_to_publish);
+        {
+            let _write_access = self.data.queue_to_publish.lock().await;
+            self.data.is_shutting_down.store(true, Ordering::SeqCst);
         }
 
-        let mut write_access = self.data.queue_to_publish.lock().await;
-        for msg in to_publish {
-            write_access.queue.push_back(msg);
+        self.data.queue_is_released.notify_waiters();
+
+        let _ =
+            tokio::time::timeout_at(deadline, self.data.wait_until_everything_is_published()).await;
+
+        let _ = self.stop_sender.send(true);
+
+        let events_publisher_task = self.events_publisher_task.lock().unwrap().take();
+
+        if let Some(mut events_publisher_task) = events_publisher_task {
+            if tokio::time::timeout_at(deadline, &mut events_publisher_task)
+                .await
+                .is_err()
+            {
+                events_publisher_task.abort();
+            }
         }
 
-        if let Err(err) = self.event_sender.send(()) {
+        let not_published = self.data.fail_not_published_messages().await;
+
+        if not_published > 0 {
             let mut ctx = HashMap::new();
             ctx.insert("topicId".to_string(), self.data.topic_id.to_string());
             self.data.logger.write_error(
-                "publish_and_forget".to_string(),
-                format!("Can not publish message. Err: {}", err),
+                "shutdown".to_string(),
+                format!(
+                    "Publisher is stopped. {} messages were not published",
+                    not_published
+                ),
                 Some(ctx),
-            )
+            );
         }
 
-        Ok(())
-    }
-    pub async fn get_queue_size(&self) -> usize {
-        let read_access = self.data.queue_to_publish.lock().await;
-        read_access.queue.len() + read_access.being_published
+        not_published
     }
 }
 
 async fn events_publisher(
     data: Arc<PublisherWithInternalQueueData>,
     mut event_receiver: UnboundedReceiver<()>,
+    mut stop_receiver: watch::Receiver<bool>,
 ) {
-    let mut to_publish = None;
+    let max_in_flight_batches = data.settings.publish_policy.get_max_in_flight_batches();
+
+    let mut in_flight = JoinSet::new();
+    let mut events_are_closed = false;
+
     loop {
-        if to_publish.is_none() {
-            tokio::sync::mpsc::UnboundedReceiver::recv(&mut event_receiver).await;
-            to_publish = data.get_messages_to_publish().await;
+        let is_stopping = *stop_receiver.borrow();
+
+        while !is_stopping && in_flight.len() < max_in_flight_batches {
+            if let Some(linger) = data.settings.publish_policy.linger {
+                if data.is_waiting_for_batch_to_fill().await {
+                    tokio::time::sleep(linger).await;
+                }
+            }
+
+            match data.get_messages_to_publish().await {
+                Some(batch) => {
+                    in_flight.spawn(publish_batch(data.clone(), batch, stop_receiver.clone()));
+                }
+                None => break,
+            }
         }
 
-        if to_publish.is_none() {
+        if in_flight.is_empty() {
+            if is_stopping || events_are_closed {
+                return;
+            }
+
+            tokio::select! {
+                event = event_receiver.recv() => {
+                    if event.is_none() {
+                        return;
+                    }
+                }
+                Ok(_) = stop_receiver.changed() => {}
+            }
+
+            continue;
+        }
+
+        if is_stopping || events_are_closed {
+            in_flight.join_next().await;
             continue;
         }
 
-        if data.publish(to_publish.as_ref().unwrap()).await {
-            data.messages_are_published().await;
-            to_publish = None;
-        } else {
-            tokio::time::sleep(std::time::Duration::from_secs(3)).await;
+        tokio::select! {
+            _ = in_flight.join_next() => {}
+            event = event_receiver.recv() => {
+                if event.is_none() {
+                    events_are_closed = true;
+                }
+            }
+            Ok(_) = stop_receiver.changed() => {}
+        }
+    }
+}
+
+async fn publish_batch(
+    data: Arc<PublisherWithInternalQueueData>,
+    batch: BatchToPublish,
+    mut stop_receiver: watch::Receiver<bool>,
+) {
+    let mut attempt_no = 0;
+
+    while !data.publish(&batch.messages).await {
+        if *stop_receiver.borrow() {
+            return;
+        }
+
+        let delay = data
+            .settings
+            .publish_policy
+            .get_retry_delay(attempt_no, &data.retry_delay_random);
+        attempt_no += 1;
+
+        tokio::select! {
+            _ = tokio::time::sleep(delay) => {}
+            Ok(_) = stop_receiver.changed() => return,
+        }
+    }
+
+    data.messages_are_published(batch.batch_id).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        publisher::{PublishPolicy, PublisherDiskSpoolSettings},
+        test_utils::{TestInterceptor, TestLogger, TestPublishMessage, TestPublisherClient},
+    };
+
+    fn create_spool_settings(test_name: &str) -> PublisherWithInternalQueueSettings {
+        let path = std::env::temp_dir().join(format!(
+            "my-sb-publisher-{}-{}",
+            test_name,
+            std::process::id()
+        ));
+
+        let _ = std::fs::remove_dir_all(&path);
+
+        PublisherWithInternalQueueSettings {
+            disk_spool: Some(PublisherDiskSpoolSettings::new(path)),
+            publish_policy: PublishPolicy {
+                retry_delay: Duration::from_millis(10),
+                max_retry_delay: Duration::from_millis(10),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    fn create_settings() -> PublisherWithInternalQueueSettings {
+        PublisherWithInternalQueueSettings {
+            publish_policy: PublishPolicy {
+                retry_delay: Duration::from_millis(10),
+                max_retry_delay: Duration::from_millis(10),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    async fn publish(publisher: &PublisherWithInternalQueue<TestPublishMessage>, no: u8) {
+        publisher
+            .publish_and_forget(
+                TestPublishMessage(no),
+                #[cfg(feature = "with-telemetry")]
+                None,
+            )
+            .await
+            .unwrap();
+    }
+
+    fn create_publisher(
+        client: &Arc<TestPublisherClient>,
+        settings: &PublisherWithInternalQueueSettings,
+    ) -> PublisherWithInternalQueue<TestPublishMessage> {
+        PublisherWithInternalQueue::new_with_settings(
+            "test-topic".to_string(),
+            client.clone(),
+            TestLogger::new(),
+            settings.clone(),
+        )
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_not_published_messages_are_replayed_after_restart() {
+        let settings = create_spool_settings("replay");
+
+        let failing_client = TestPublisherClient::new();
+        failing_client.fail_next_attempts(usize::MAX);
+
+        let publisher = create_publisher(&failing_client, &settings);
+
+        for i in 1..=3 {
+            publish(&publisher, i).await;
+        }
+
+        assert_eq!(3, publisher.shutdown(Duration::from_millis(50)).await);
+        drop(publisher);
+
+        let client = TestPublisherClient::new();
+        let publisher = create_publisher(&client, &settings);
+
+        assert_eq!(3, publisher.get_queue_size().await);
+        assert_eq!(true, publisher.flush(Duration::from_secs(1)).await);
+        assert_eq!(vec![1, 2, 3], client.get_published_contents());
+        drop(publisher);
+
+        let client = TestPublisherClient::new();
+        let publisher = create_publisher(&client, &settings);
+
+        assert_eq!(0, publisher.get_queue_size().await);
+        drop(publisher);
+
+        let _ = std::fs::remove_dir_all(&settings.disk_spool.unwrap().path);
+    }
+
+    #[tokio::test]
+    async fn test_flush_waits_until_messages_are_published() {
+        let client = TestPublisherClient::new();
+        client.fail_next_attempts(2);
+
+        let publisher = create_publisher(&client, &create_settings());
+
+        for i in 1..=3 {
+            publish(&publisher, i).await;
+        }
+
+        assert_eq!(true, publisher.flush(Duration::from_secs(1)).await);
+        assert_eq!(vec![1, 2, 3], client.get_published_contents());
+        assert_eq!(0, publisher.get_queue_size().await);
+    }
+
+    #[tokio::test]
+    async fn test_flush_times_out() {
+        let client = TestPublisherClient::new();
+        client.fail_next_attempts(usize::MAX);
+
+        let publisher = create_publisher(&client, &create_settings());
+        publish(&publisher, 1).await;
+
+        assert_eq!(false, publisher.flush(Duration::from_millis(50)).await);
+        assert_eq!(1, publisher.get_queue_size().await);
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_stops_retries_and_fails_in_flight_batch() {
+        let client = TestPublisherClient::new();
+        client.fail_next_attempts(usize::MAX);
+
+        let publisher = create_publisher(&client, &create_settings());
+
+        let confirmation = publisher
+            .publish_chunk_with_confirmation(
+                vec![TestPublishMessage(1), TestPublishMessage(2)],
+                #[cfg(feature = "with-telemetry")]
+                None,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(2, publisher.shutdown(Duration::from_millis(50)).await);
+        assert!(matches!(
+            confirmation.await,
+            Err(PublishError::PublisherIsStopped)
+        ));
+
+        let attempts = client.get_attempts();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(attempts, client.get_attempts());
+
+        let result = publisher
+            .publish_and_forget(
+                TestPublishMessage(3),
+                #[cfg(feature = "with-telemetry")]
+                None,
+            )
+            .await;
+
+        assert!(matches!(result, Err(PublishError::PublisherIsStopped)));
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_waits_for_publish_in_progress() {
+        let client = TestPublisherClient::new_with_delay(Duration::from_millis(100));
+        let publisher = create_publisher(&client, &create_settings());
+
+        publish(&publisher, 1).await;
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        assert_eq!(0, publisher.shutdown(Duration::from_millis(300)).await);
+        assert_eq!(vec![1], client.get_published_contents());
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_does_not_exceed_timeout() {
+        let client = TestPublisherClient::new_with_delay(Duration::from_secs(1));
+        let publisher = create_publisher(&client, &create_settings());
+
+        publish(&publisher, 1).await;
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        let started = std::time::Instant::now();
+        assert_eq!(1, publisher.shutdown(Duration::from_millis(100)).await);
+        assert!(started.elapsed() < Duration::from_millis(180));
+    }
+
+    #[tokio::test]
+    async fn test_queue_size_with_several_batches_in_flight() {
+        let client = TestPublisherClient::new_with_delay(Duration::from_millis(100));
+        client.fail_next_attempts(2);
+
+        let mut settings = create_settings();
+        settings.publish_policy.max_batch_messages = Some(1);
+        settings.publish_policy.max_in_flight_batches = 3;
+
+        let publisher = create_publisher(&client, &settings);
+
+        for i in 1..=5 {
+            publish(&publisher, i).await;
+        }
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        {
+            let read_access = publisher.data.queue_to_publish.lock().await;
+            assert_eq!(3, read_access.in_flight_batches.len());
+            assert_eq!(3, read_access.being_published);
+            assert_eq!(3, read_access.being_published_size_in_bytes);
+            assert_eq!(2, read_access.queue.len());
+        }
+
+        assert_eq!(5, publisher.get_queue_size().await);
+
+        assert_eq!(true, publisher.flush(Duration::from_secs(2)).await);
+
+        let mut published = client.get_published_contents();
+        published.sort();
+        assert_eq!(vec![1, 2, 3, 4, 5], published);
+        assert_eq!(7, client.get_attempts());
+
+        let read_access = publisher.data.queue_to_publish.lock().await;
+        assert_eq!(0, read_access.being_published);
+        assert_eq!(0, read_access.being_published_size_in_bytes);
+        assert_eq!(0, read_access.in_flight_batches.len());
+        assert_eq!(0, read_access.get_messages_amount());
+    }
+
+    #[tokio::test]
+    async fn test_keep_order_publishes_one_batch_at_a_time() {
+        let client = TestPublisherClient::new_with_delay(Duration::from_millis(20));
+        client.fail_next_attempts(1);
+
+        let mut settings = create_settings();
+        settings.publish_policy.max_batch_messages = Some(1);
+        settings.publish_policy.max_in_flight_batches = 3;
+        settings.publish_policy.keep_order = true;
+
+        let publisher = create_publisher(&client, &settings);
+
+        for i in 1..=3 {
+            publish(&publisher, i).await;
         }
+
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert_eq!(
+            1,
+            publisher.data.queue_to_publish.lock().await.being_published
+        );
+
+        assert_eq!(true, publisher.flush(Duration::from_secs(1)).await);
+        assert_eq!(vec![1, 2, 3], client.get_published_contents());
+    }
+
+    #[tokio::test]
+    async fn test_interceptors_are_applied_to_queued_messages() {
+        let client = TestPublisherClient::new();
+        let logger = TestLogger::new();
+        let calls = Arc::new(std::sync::Mutex::new(Vec::new()));
+
+        let mut settings = create_settings();
+        settings.interceptors.add(Arc::new(TestInterceptor {
+            name: "a",
+            calls: calls.clone(),
+            reject: false,
+        }));
+
+        let publisher: PublisherWithInternalQueue<TestPublishMessage> =
+            PublisherWithInternalQueue::new_with_settings(
+                "test-topic".to_string(),
+                client.clone(),
+                logger.clone(),
+                settings,
+            )
+            .unwrap();
+
+        publish(&publisher, 1).await;
+        assert_eq!(true, publisher.flush(Duration::from_secs(1)).await);
+
+        assert_eq!(vec!["a:before", "a:after:1"], *calls.lock().unwrap());
+        assert_eq!(vec![vec![1, b'a']], client.get_published_contents_raw());
+    }
+
+    #[tokio::test]
+    async fn test_rejected_message_is_logged_and_not_queued() {
+        let client = TestPublisherClient::new();
+        let logger = TestLogger::new();
+
+        let mut settings = create_settings();
+        settings.interceptors.add(Arc::new(TestInterceptor {
+            name: "a",
+            calls: Arc::new(std::sync::Mutex::new(Vec::new())),
+            reject: true,
+        }));
+
+        let publisher: PublisherWithInternalQueue<TestPublishMessage> =
+            PublisherWithInternalQueue::new_with_settings(
+                "test-topic".to_string(),
+                client.clone(),
+                logger.clone(),
+                settings,
+            )
+            .unwrap();
+
+        let result = publisher
+            .publish_and_forget(
+                TestPublishMessage(1),
+                #[cfg(feature = "with-telemetry")]
+                None,
+            )
+            .await;
+
+        assert!(matches!(result, Err(PublishError::Other(_))));
+        assert_eq!(true, logger.has_message("rejected by interceptor"));
+        assert_eq!(0, publisher.get_queue_size().await);
+    }
+
+    #[test]
+    fn test_spool_open_error_is_returned() {
+        let file_name =
+            std::env::temp_dir().join(format!("my-sb-publisher-not-a-dir-{}", std::process::id()));
+        std::fs::write(&file_name, b"").unwrap();
+
+        let settings = PublisherWithInternalQueueSettings {
+            disk_spool: Some(PublisherDiskSpoolSettings::new(&file_name)),
+            ..Default::default()
+        };
+
+        let result = PublisherWithInternalQueue::<TestPublishMessage>::new_with_settings(
+            "test-topic".to_string(),
+            TestPublisherClient::new(),
+            TestLogger::new(),
+            settings,
+        );
+
+        assert_eq!(true, result.is_err());
+
+        std::fs::remove_file(&file_name).unwrap();
     }
 }
@@ -0,0 +1,56 @@
+use super::{super::PublishInterceptors, PublishPolicy, PublisherDiskSpoolSettings};
+
+#[derive(Debug, Clone, Copy)]
+pub enum QueueOverflowPolicy {
+    Wait,
+    Reject,
+    DropOldest,
+    DropNewest,
+}
+
+#[derive(Debug, Clone)]
+pub struct PublisherWithInternalQueueSettings {
+    pub max_messages: Option<usize>,
+    pub max_bytes: Option<usize>,
+    pub overflow_policy: QueueOverflowPolicy,
+    pub disk_spool: Option<PublisherDiskSpoolSettings>,
+    pub publish_policy: PublishPolicy,
+    pub interceptors: PublishInterceptors,
+}
+
+impl PublisherWithInternalQueueSettings {
+    pub fn can_ever_fit(&self, messages_amount: usize, size_in_bytes: usize) -> bool {
+        if let Some(max_messages) = self.max_messages {
+            if messages_amount > max_messages {
+                return false;
+            }
+        }
+
+        if let Some(max_bytes) = self.max_bytes {
+            if size_in_bytes > max_bytes {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+impl Default for PublisherWithInternalQueueSettings {
+    fn default() -> Self {
+        Self {
+            max_messages: None,
+            max_bytes: None,
+            overflow_policy: QueueOverflowPolicy::Wait,
+            disk_spool: None,
+            publish_policy: PublishPolicy::default(),
+            interceptors: PublishInterceptors::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PublisherQueueRemainingCapacity {
+    pub messages: Option<usize>,
+    pub bytes: Option<usize>,
+}
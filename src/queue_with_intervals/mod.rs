@@ -1,7 +1,8 @@
 pub use queue_index_range::QueueIndexRange;
-pub use queue_with_intervals::QueueWithIntervals;
+pub use queue_with_intervals::{QueueWithIntervals, QueueWithIntervalsError};
 
 mod iterator;
 mod queue_index_range;
 mod queue_with_intervals;
+mod set_operations;
 pub mod split_queue;
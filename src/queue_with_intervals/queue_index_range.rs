@@ -1,4 +1,6 @@
 
+use super::QueueWithIntervalsError;
+
 pub enum QueueIndexRangeCompare {
     Below,
     Inside,
@@ -60,22 +62,48 @@ impl QueueIndexRange {
     }
 
     pub fn is_my_interval_to_remove(&self, id: i64) -> bool {
+        match self.try_is_my_interval_to_remove(id) {
+            Ok(result) => result,
+            Err(_) => {
+                panic!("We are trying to find interval to remove but we bumped empty interval")
+            }
+        }
+    }
+
+    pub fn try_is_my_interval_to_remove(&self, id: i64) -> Result<bool, QueueWithIntervalsError> {
         if self.is_empty() {
-            panic!("We are trying to find interval to remove but we bumped empty interval");
+            return Err(QueueWithIntervalsError::EmptyInterval);
         }
 
-        id >= self.from_id && id <= self.to_id
+        Ok(id >= self.from_id && id <= self.to_id)
     }
 
     pub fn init(&mut self) {
         self.to_id = self.from_id - 1;
     }
 
-    pub fn remove(&mut self, message_id: i64) -> RemoveResult {
+    pub fn try_remove(&mut self, message_id: i64) -> Result<RemoveResult, QueueWithIntervalsError> {
+        if self.is_empty() {
+            return Err(QueueWithIntervalsError::EmptyInterval);
+        }
 
+        if !self.is_in_my_interval(message_id) {
+            return Err(QueueWithIntervalsError::MessagesNotFound);
+        }
+
+        Ok(self.remove(message_id))
+    }
+
+    pub fn remove(&mut self, message_id: i64) -> RemoveResult {
+        if !self.is_in_my_interval(message_id) {
+            return RemoveResult::NoUpdate;
+        }
 
         if self.from_id == message_id && self.to_id == message_id{
-            self.from_id += 1;
+            match self.from_id.checked_add(1) {
+                Some(from_id) => self.from_id = from_id,
+                None => self.to_id -= 1,
+            }
 
             return RemoveResult::RemoveItem;
         }
@@ -121,27 +149,46 @@ impl QueueIndexRange {
     }
 
     pub fn enqueue(&mut self, id: i64) {
+        match self.try_enqueue(id) {
+            Ok(_) => {}
+            Err(QueueWithIntervalsError::AlreadyExists(_)) => {
+                panic!(
+                    "Warning.... Something went wrong. We are enqueueing the Value {} which is already in the queue. Range: {:?}. ",
+                    id, self,
+                );
+            }
+            Err(_) => {
+                panic!(
+                    "Something went wrong. Invalid interval is chosen to enqueue. Range: {:?}. NewValue: {}",
+                    self, id
+                );
+            }
+        }
+    }
+
+    pub fn try_enqueue(&mut self, id: i64) -> Result<(), QueueWithIntervalsError> {
         if self.is_empty() {
             self.from_id = id;
             self.to_id = id;
-            return;
+            return Ok(());
         }
 
-        if self.from_id >= id && self.to_id <= id {
-            panic!(
-                "Warning.... Something went wrong. We are enqueueing the Value {} which is already in the queue. Range: {:?}. ",
-                id, self, 
-            );
-        } else if self.to_id + 1 == id {
+        if self.is_in_my_interval(id) {
+            return Err(QueueWithIntervalsError::AlreadyExists(id));
+        }
+
+        if self.to_id + 1 == id {
             self.to_id = id;
         } else if self.from_id - 1 == id {
             self.from_id = id
         } else {
-            panic!(
-                "Something went wrong. Invalid interval is chosen to enqueue. Range: {:?}. NewValue: {}",
-                self, id
-            );
+            return Err(QueueWithIntervalsError::NotAdjacent {
+                range: self.clone(),
+                id,
+            });
         }
+
+        Ok(())
     }
 
     pub fn try_merge_next(&mut self, next_item: &QueueIndexRange) -> bool {
@@ -159,12 +206,12 @@ impl QueueIndexRange {
             self.to_id = id_to_join;
         }
 
-        if id_to_join == self.from_id - 1 {
+        if self.from_id.checked_sub(1) == Some(id_to_join) {
             self.from_id = id_to_join;
             return true;
         }
 
-        if id_to_join == self.to_id + 1 {
+        if self.to_id.checked_add(1) == Some(id_to_join) {
             self.to_id = id_to_join;
             return true;
         }
@@ -277,4 +324,66 @@ mod tests {
         let _result = index_range.compare_with(11).unwrap();
         assert_eq!(true, matches!(QueueIndexRangeCompare::Above, _result));
     }
+
+    #[test]
+    fn test_try_enqueue() {
+        let mut index_range = QueueIndexRange::restore(5, 10);
+
+        assert!(matches!(
+            index_range.try_enqueue(7),
+            Err(QueueWithIntervalsError::AlreadyExists(7))
+        ));
+
+        assert!(matches!(
+            index_range.try_enqueue(20),
+            Err(QueueWithIntervalsError::NotAdjacent { id: 20, .. })
+        ));
+
+        index_range.try_enqueue(11).unwrap();
+        index_range.try_enqueue(4).unwrap();
+
+        assert_eq!(4, index_range.from_id);
+        assert_eq!(11, index_range.to_id);
+    }
+
+    #[test]
+    fn test_try_is_my_interval_to_remove() {
+        let index_range = QueueIndexRange::new_empty(5);
+
+        assert!(matches!(
+            index_range.try_is_my_interval_to_remove(5),
+            Err(QueueWithIntervalsError::EmptyInterval)
+        ));
+
+        let mut index_range = QueueIndexRange::restore(5, 10);
+        assert_eq!(true, index_range.try_is_my_interval_to_remove(5).unwrap());
+        assert_eq!(false, index_range.try_is_my_interval_to_remove(11).unwrap());
+
+        assert!(matches!(
+            index_range.try_remove(11),
+            Err(QueueWithIntervalsError::MessagesNotFound)
+        ));
+        assert!(matches!(
+            index_range.try_remove(7),
+            Ok(RemoveResult::InsertNew(_))
+        ));
+    }
+
+    #[test]
+    fn test_remove_outside_of_interval_keeps_it() {
+        let mut index_range = QueueIndexRange::restore(5, 10);
+
+        assert!(matches!(index_range.remove(4), RemoveResult::NoUpdate));
+        assert!(matches!(index_range.remove(11), RemoveResult::NoUpdate));
+        assert_eq!(5, index_range.from_id);
+        assert_eq!(10, index_range.to_id);
+
+        let mut index_range = QueueIndexRange::new_empty(5);
+        assert!(matches!(index_range.remove(5), RemoveResult::NoUpdate));
+        assert_eq!(true, index_range.is_empty());
+
+        let mut index_range = QueueIndexRange::new_with_single_value(i64::MAX);
+        assert!(matches!(index_range.remove(i64::MAX), RemoveResult::RemoveItem));
+        assert_eq!(true, index_range.is_empty());
+    }
 }
@@ -1,42 +1,61 @@
 use crate::queue_with_intervals::queue_index_range::{QueueIndexRange, RemoveResult};
 
-use super::{iterator::QueueWithIntervalsIterator, queue_index_range::QueueIndexRangeCompare};
+use super::iterator::QueueWithIntervalsIterator;
 
 #[derive(Debug, Clone)]
 pub enum QueueWithIntervalsError {
     MessagesNotFound,
     QueueIsEmpty,
+    AlreadyExists(i64),
+    NotAdjacent { range: QueueIndexRange, id: i64 },
+    EmptyInterval,
+    InvalidInterval { index: usize },
+    NotSorted { index: usize },
+    Overlapping { index: usize },
+    Adjacent { index: usize },
 }
 
 #[derive(Debug, Clone)]
 pub struct QueueWithIntervals {
-    pub intervals: Vec<QueueIndexRange>,
+    pub(crate) intervals: Vec<QueueIndexRange>,
+    len: i64,
 }
 
 impl QueueWithIntervals {
     pub fn new() -> QueueWithIntervals {
         Self {
             intervals: Vec::new(),
+            len: 0,
         }
     }
 
     pub fn restore(intervals: Vec<QueueIndexRange>) -> Self {
-        return Self { intervals };
+        let len = calc_len(&intervals);
+        return Self { intervals, len };
     }
 
-    pub fn from_single_interval(from_id: i64, to_id: i64) -> Self {
-        let mut result = Self {
-            intervals: Vec::new(),
-        };
-
-        result.intervals.push(QueueIndexRange { from_id, to_id });
+    pub fn try_restore(intervals: Vec<QueueIndexRange>) -> Result<Self, QueueWithIntervalsError> {
+        let result = Self::restore(intervals);
+        result.validate()?;
+        Ok(result)
+    }
 
-        result
+    pub fn from_single_interval(from_id: i64, to_id: i64) -> Self {
+        Self::restore(vec![QueueIndexRange { from_id, to_id }])
     }
 
     pub fn reset(&mut self, intervals: Vec<QueueIndexRange>) {
         self.intervals.clear();
-        self.intervals.extend(intervals)
+        self.intervals.extend(intervals);
+        self.len = calc_len(&self.intervals);
+    }
+
+    pub fn try_reset(
+        &mut self,
+        intervals: Vec<QueueIndexRange>,
+    ) -> Result<(), QueueWithIntervalsError> {
+        *self = Self::try_restore(intervals)?;
+        Ok(())
     }
 
     pub fn clean(&mut self) {
@@ -45,9 +64,35 @@ impl QueueWithIntervals {
             first_interval.to_id = -1;
         }
 
-        while self.intervals.len() > 1 {
-            self.intervals.remove(self.intervals.len() - 1);
+        self.intervals.truncate(1);
+        self.len = 0;
+    }
+
+    pub fn get_intervals(&self) -> &[QueueIndexRange] {
+        &self.intervals
+    }
+
+    fn has_only_empty_interval(&self) -> bool {
+        match self.intervals.get(0) {
+            Some(first) => first.is_empty(),
+            None => true,
+        }
+    }
+
+    fn find_interval_index(&self, id: i64) -> Option<usize> {
+        if self.has_only_empty_interval() {
+            return None;
+        }
+
+        let index = self.intervals.partition_point(|item| item.to_id < id);
+
+        let item = self.intervals.get(index)?;
+
+        if item.from_id <= id {
+            return Some(index);
         }
+
+        None
     }
 
     pub fn remove(&mut self, id: i64) -> Result<(), QueueWithIntervalsError> {
@@ -55,245 +100,308 @@ impl QueueWithIntervals {
             return Err(QueueWithIntervalsError::QueueIsEmpty);
         }
 
-        for index in 0..self.intervals.len() {
-            let item = self.intervals.get_mut(index).unwrap();
-
-            if item.is_my_interval_to_remove(id) {
-                let remove_result = item.remove(id);
-
-                match remove_result {
-                    RemoveResult::NoUpdate => {}
-                    RemoveResult::InsertNew(new_item) => {
-                        self.intervals.insert(index + 1, new_item);
-                    }
-                    RemoveResult::RemoveItem => {
-                        self.intervals.remove(index);
-                    }
-                }
+        let index = match self.find_interval_index(id) {
+            Some(index) => index,
+            None => return Err(QueueWithIntervalsError::MessagesNotFound),
+        };
 
-                return Ok(());
+        match self.intervals[index].remove(id) {
+            RemoveResult::NoUpdate => {}
+            RemoveResult::InsertNew(new_item) => {
+                self.intervals.insert(index + 1, new_item);
+            }
+            RemoveResult::RemoveItem => {
+                self.intervals.remove(index);
             }
         }
 
-        return Err(QueueWithIntervalsError::MessagesNotFound);
+        self.len -= 1;
+
+        Ok(())
     }
 
     pub fn enqueue(&mut self, message_id: i64) {
-        match self.intervals.get_mut(0) {
-            Some(el) => {
-                if el.is_empty() {
-                    el.from_id = message_id;
-                    el.to_id = message_id;
-                    return;
-                }
-            }
+        if self.has_only_empty_interval() {
+            self.set_single_interval(QueueIndexRange::new_with_single_value(message_id));
+            return;
+        }
+
+        let index = match message_id.checked_sub(1) {
+            Some(prev_id) => self.intervals.partition_point(|item| item.to_id < prev_id),
+            None => 0,
+        };
+
+        let el = match self.intervals.get_mut(index) {
+            Some(el) => el,
             None => {
-                let item = QueueIndexRange::new_with_single_value(message_id);
-                self.intervals.push(item);
+                self.intervals
+                    .push(QueueIndexRange::new_with_single_value(message_id));
+                self.len += 1;
                 return;
             }
+        };
+
+        if el.is_in_my_interval(message_id) {
+            return;
         }
 
-        let mut found_index = None;
+        if !el.try_join(message_id) {
+            self.intervals
+                .insert(index, QueueIndexRange::new_with_single_value(message_id));
+            self.len += 1;
+            return;
+        }
 
-        for index in 0..self.intervals.len() {
-            let el = self.intervals.get_mut(index).unwrap();
+        self.len += 1;
 
-            if el.try_join(message_id) {
-                found_index = Some(index);
-                break;
+        if let Some(next_el) = self.intervals.get(index + 1).cloned() {
+            if self.intervals[index].try_join_with_the_next_one(next_el) {
+                self.intervals.remove(index + 1);
             }
+        }
+    }
 
-            if message_id < el.from_id - 1 {
-                let item = QueueIndexRange::new_with_single_value(message_id);
-                self.intervals.insert(index, item);
-                found_index = Some(index);
-                break;
-            }
+    pub fn try_enqueue(&mut self, message_id: i64) -> Result<(), QueueWithIntervalsError> {
+        if self.has_message(message_id) {
+            return Err(QueueWithIntervalsError::AlreadyExists(message_id));
         }
 
-        match found_index {
-            Some(index_we_handled) => {
-                if index_we_handled > 0 {
-                    let current_el = self.intervals.get(index_we_handled).unwrap().clone();
-                    let before_el = self.intervals.get_mut(index_we_handled - 1).unwrap();
-                    if before_el.try_join_with_the_next_one(current_el) {
-                        self.intervals.remove(index_we_handled);
-                    }
-                }
+        self.enqueue(message_id);
+        Ok(())
+    }
 
-                if index_we_handled < self.intervals.len() - 1 {
-                    let after_el = self.intervals.get(index_we_handled + 1).unwrap().clone();
+    fn set_single_interval(&mut self, range: QueueIndexRange) {
+        self.len = range.len();
+        self.intervals.clear();
+        self.intervals.push(range);
+    }
 
-                    let current_el = self.intervals.get_mut(index_we_handled).unwrap();
-                    if current_el.try_join_with_the_next_one(after_el) {
-                        self.intervals.remove(index_we_handled + 1);
-                    }
-                }
-            }
-            None => {
-                let item = QueueIndexRange::new_with_single_value(message_id);
-                self.intervals.push(item);
-                return;
-            }
-        }
+    pub fn merge_with(&mut self, other_queue: &QueueWithIntervals) {
+        *self = self.union(other_queue);
     }
 
-    fn get_indexes_it_covers(&self, range_to_insert: &QueueIndexRange) -> Option<Vec<usize>> {
-        let mut result = Vec::new();
+    pub fn try_merge_with(
+        &mut self,
+        other_queue: &QueueWithIntervals,
+    ) -> Result<(), QueueWithIntervalsError> {
+        other_queue.validate()?;
+        self.merge_with(other_queue);
+        Ok(())
+    }
 
-        for index in 0..self.intervals.len() {
-            let el = self.intervals.get(index).unwrap();
+    pub fn enqueue_range(&mut self, range_to_insert: &QueueIndexRange) {
+        if range_to_insert.is_empty() {
+            return;
+        }
 
-            if range_to_insert.from_id <= el.from_id && range_to_insert.to_id >= el.to_id {
-                result.push(index);
-            }
+        if self.has_only_empty_interval() {
+            self.set_single_interval(range_to_insert.clone());
+            return;
         }
 
-        if result.len() == 0 {
-            return None;
+        let from_index = match range_to_insert.from_id.checked_sub(1) {
+            Some(prev_id) => self.intervals.partition_point(|item| item.to_id < prev_id),
+            None => 0,
+        };
+
+        let to_index = match range_to_insert.to_id.checked_add(1) {
+            Some(next_id) => self
+                .intervals
+                .partition_point(|item| item.from_id <= next_id),
+            None => self.intervals.len(),
+        };
+
+        if from_index == to_index {
+            self.intervals.insert(from_index, range_to_insert.clone());
+            self.len += range_to_insert.len();
+            return;
         }
 
-        Some(result)
-    }
+        let from_id = self.intervals[from_index]
+            .from_id
+            .min(range_to_insert.from_id);
+        let to_id = self.intervals[to_index - 1]
+            .to_id
+            .max(range_to_insert.to_id);
 
-    fn compact_it(&mut self) {
-        let mut index = 0;
-        while index < self.intervals.len() - 1 {
-            let el_to_id = self.intervals.get(index).unwrap().to_id;
-            let next = self.intervals.get(index + 1).unwrap().clone();
+        let joined_len: i64 = self.intervals[from_index..to_index]
+            .iter()
+            .map(|item| item.len())
+            .sum();
 
-            if next.can_be_joined_to_interval_from_the_left(el_to_id) {
-                let removed = self.intervals.remove(index + 1);
-                self.intervals.get_mut(index).unwrap().to_id = removed.to_id;
-                continue;
-            }
+        self.intervals.splice(
+            from_index..to_index,
+            [QueueIndexRange::restore(from_id, to_id)],
+        );
 
-            index += 1;
-        }
+        self.len += to_id - from_id + 1 - joined_len;
     }
 
-    pub fn merge_with(&mut self, other_queue: &QueueWithIntervals) {
-        for range in &other_queue.intervals {
-            self.enqueue_range(range);
+    pub fn try_enqueue_range(
+        &mut self,
+        range_to_insert: &QueueIndexRange,
+    ) -> Result<(), QueueWithIntervalsError> {
+        if range_to_insert.is_empty() {
+            return Err(QueueWithIntervalsError::EmptyInterval);
         }
-    }
 
-    pub fn enqueue_range(&mut self, range_to_insert: &QueueIndexRange) {
-        let first_el_result = self.intervals.get_mut(0);
-
-        match first_el_result {
-            Some(first_el) => {
-                if first_el.is_empty() {
-                    first_el.from_id = range_to_insert.from_id;
-                    first_el.to_id = range_to_insert.to_id;
-                    return;
-                }
-            }
+        if !self.has_only_empty_interval() {
+            let index = self
+                .intervals
+                .partition_point(|item| item.to_id < range_to_insert.from_id);
 
-            None => {
-                self.intervals.push(range_to_insert.clone());
-                return;
+            if let Some(item) = self.intervals.get(index) {
+                if item.from_id <= range_to_insert.to_id {
+                    return Err(QueueWithIntervalsError::AlreadyExists(
+                        item.from_id.max(range_to_insert.from_id),
+                    ));
+                }
             }
         }
 
-        let cover_indexes = self.get_indexes_it_covers(range_to_insert);
+        self.enqueue_range(range_to_insert);
+        Ok(())
+    }
+
+    pub fn dequeue(&mut self) -> Option<i64> {
+        let first_interval = self.intervals.get_mut(0)?;
 
-        if let Some(mut cover_indexes) = cover_indexes {
-            let first_index = cover_indexes[0];
-            let mut from_id = self.intervals[first_index].from_id;
+        let result = first_interval.dequeue();
 
-            if range_to_insert.from_id < from_id {
-                from_id = range_to_insert.from_id;
-            }
+        if first_interval.is_empty() && self.intervals.len() > 1 {
+            self.intervals.remove(0);
+        }
+
+        if result.is_some() {
+            self.len -= 1;
+        }
 
-            let last = cover_indexes.last().unwrap();
+        result
+    }
 
-            let mut to_id = self.intervals[*last].to_id;
+    pub fn dequeue_range(&mut self, max_count: usize) -> Vec<QueueIndexRange> {
+        let mut result = Vec::new();
+        let max_count = i64::try_from(max_count).unwrap_or(i64::MAX);
+        let mut remaining = max_count;
+        let mut dequeued_intervals = 0;
 
-            if range_to_insert.to_id > to_id {
-                to_id = range_to_insert.to_id;
+        for interval in self.intervals.iter_mut() {
+            if remaining == 0 || interval.is_empty() {
+                break;
             }
 
-            while cover_indexes.len() > 1 {
-                self.intervals.remove(cover_indexes.len() - 1);
-                cover_indexes.remove(0);
+            if interval.len() <= remaining {
+                remaining -= interval.len();
+                result.push(interval.clone());
+                dequeued_intervals += 1;
+            } else {
+                let to_id = interval.from_id + remaining - 1;
+                result.push(QueueIndexRange::restore(interval.from_id, to_id));
+                interval.from_id = to_id + 1;
+                remaining = 0;
             }
+        }
+
+        if dequeued_intervals > 0 && dequeued_intervals == self.intervals.len() {
+            self.intervals.truncate(1);
+            let first_interval = &mut self.intervals[0];
+            first_interval.to_id = result.last().unwrap().to_id;
+            first_interval.from_id = first_interval.to_id + 1;
+        } else {
+            self.intervals.drain(..dequeued_intervals);
+        }
 
-            let el = self.intervals.get_mut(first_index).unwrap();
-            el.from_id = from_id;
-            el.to_id = to_id;
+        self.len -= max_count - remaining;
 
-            self.compact_it();
+        result
+    }
+
+    pub fn remove_range(&mut self, range_to_remove: &QueueIndexRange) -> QueueWithIntervals {
+        if range_to_remove.is_empty() || self.has_only_empty_interval() {
+            return QueueWithIntervals::new();
         }
 
-        let mut from_index = None;
+        let from_index = self
+            .intervals
+            .partition_point(|item| item.to_id < range_to_remove.from_id);
 
-        for index in 0..self.intervals.len() {
-            let current_range = self.intervals.get(index).unwrap().clone();
+        let to_index = self
+            .intervals
+            .partition_point(|item| item.from_id <= range_to_remove.to_id);
 
-            if current_range.from_id <= range_to_insert.from_id
-                && current_range.to_id >= range_to_insert.to_id
-            {
-                return;
-            }
+        if from_index >= to_index {
+            return QueueWithIntervals::new();
+        }
 
-            if current_range.can_be_joined_to_interval_from_the_left(range_to_insert.to_id) {
-                self.intervals.get_mut(index).unwrap().from_id = range_to_insert.from_id;
-                return;
-            }
+        let mut removed = Vec::with_capacity(to_index - from_index);
+        let mut rest = Vec::with_capacity(2);
 
-            if range_to_insert.to_id < current_range.from_id - 1 {
-                self.intervals.insert(index, range_to_insert.clone());
-                return;
+        for interval in &self.intervals[from_index..to_index] {
+            if interval.from_id < range_to_remove.from_id {
+                rest.push(QueueIndexRange::restore(
+                    interval.from_id,
+                    range_to_remove.from_id - 1,
+                ));
             }
 
-            if current_range.can_be_joined_to_interval_from_the_right(range_to_insert.from_id) {
-                if index == self.intervals.len() - 1 {
-                    self.intervals.get_mut(index).unwrap().to_id = range_to_insert.to_id;
-                    return;
-                }
+            removed.push(QueueIndexRange::restore(
+                interval.from_id.max(range_to_remove.from_id),
+                interval.to_id.min(range_to_remove.to_id),
+            ));
 
-                let next_range = self.intervals.get_mut(index + 1).unwrap();
+            if interval.to_id > range_to_remove.to_id {
+                rest.push(QueueIndexRange::restore(
+                    range_to_remove.to_id + 1,
+                    interval.to_id,
+                ));
+            }
+        }
 
-                if range_to_insert.to_id < next_range.from_id - 1 {
-                    self.intervals.get_mut(index).unwrap().to_id = range_to_insert.to_id;
-                    return;
-                }
+        self.intervals.splice(from_index..to_index, rest);
 
-                from_index = Some(index);
-                break;
-            }
+        if self.intervals.is_empty() {
+            let last_removed_id = removed[removed.len() - 1].to_id;
+            self.intervals.push(QueueIndexRange::new_empty(
+                last_removed_id.saturating_add(1),
+            ));
         }
 
-        if from_index.is_none() {
-            self.intervals.push(range_to_insert.clone());
-            return;
+        let removed = QueueWithIntervals::restore(removed);
+        self.len -= removed.len();
+        removed
+    }
+
+    pub fn try_remove_range(
+        &mut self,
+        range_to_remove: &QueueIndexRange,
+    ) -> Result<QueueWithIntervals, QueueWithIntervalsError> {
+        if range_to_remove.is_empty() {
+            return Err(QueueWithIntervalsError::EmptyInterval);
         }
 
-        let from_index = from_index.unwrap();
-        while from_index < self.intervals.len() - 1 {
-            let next_range = self.intervals.remove(from_index + 1);
+        let removed = self.remove_range(range_to_remove);
 
-            if next_range.can_be_joined_to_interval_from_the_left(range_to_insert.to_id) {
-                self.intervals.get_mut(from_index).unwrap().to_id = next_range.to_id;
-                return;
-            }
+        if removed.len() == 0 {
+            return Err(QueueWithIntervalsError::MessagesNotFound);
         }
 
-        self.intervals.push(range_to_insert.clone());
+        Ok(removed)
     }
 
-    pub fn dequeue(&mut self) -> Option<i64> {
-        let first_interval = self.intervals.get_mut(0)?;
+    pub fn remove_below(&mut self, id: i64) -> QueueWithIntervals {
+        if id == i64::MIN {
+            return QueueWithIntervals::new();
+        }
 
-        let result = first_interval.dequeue();
+        self.remove_range(&QueueIndexRange::restore(i64::MIN, id - 1))
+    }
 
-        if first_interval.is_empty() && self.intervals.len() > 1 {
-            self.intervals.remove(0);
+    pub fn remove_above(&mut self, id: i64) -> QueueWithIntervals {
+        if id == i64::MAX {
+            return QueueWithIntervals::new();
         }
 
-        result
+        self.remove_range(&QueueIndexRange::restore(id + 1, i64::MAX))
     }
 
     pub fn peek(&self) -> Option<i64> {
@@ -303,13 +411,7 @@ impl QueueWithIntervals {
     }
 
     pub fn len(&self) -> i64 {
-        let mut result = 0i64;
-
-        for i in 0..self.intervals.len() {
-            result = result + self.intervals[i].len();
-        }
-
-        result
+        self.len
     }
 
     pub fn get_snapshot(&self) -> Vec<QueueIndexRange> {
@@ -337,18 +439,54 @@ impl QueueWithIntervals {
     }
 
     pub fn has_message(&self, id: i64) -> bool {
-        for item in &self.intervals {
-            if let Some(range) = item.compare_with(id) {
-                if let QueueIndexRangeCompare::Inside = range {
-                    return true;
-                }
+        self.find_interval_index(id).is_some()
+    }
+
+    pub fn validate(&self) -> Result<(), QueueWithIntervalsError> {
+        if self.intervals.len() == 1 {
+            let interval = &self.intervals[0];
+
+            if interval.to_id.checked_add(1) == Some(interval.from_id) {
+                return Ok(());
+            }
+        }
+
+        for (index, item) in self.intervals.iter().enumerate() {
+            if item.is_empty() {
+                return Err(QueueWithIntervalsError::InvalidInterval { index });
+            }
+
+            if index == 0 {
+                continue;
+            }
+
+            let prev = &self.intervals[index - 1];
+
+            if item.from_id < prev.from_id {
+                return Err(QueueWithIntervalsError::NotSorted { index });
+            }
+
+            if item.from_id <= prev.to_id {
+                return Err(QueueWithIntervalsError::Overlapping { index });
+            }
+
+            if item.from_id == prev.to_id + 1 {
+                return Err(QueueWithIntervalsError::Adjacent { index });
             }
         }
 
-        return false;
+        Ok(())
     }
 }
 
+fn calc_len(intervals: &[QueueIndexRange]) -> i64 {
+    intervals
+        .iter()
+        .filter(|item| !item.is_empty())
+        .map(|item| item.len())
+        .sum()
+}
+
 impl IntoIterator for QueueWithIntervals {
     type Item = i64;
 
@@ -904,4 +1042,345 @@ mod tests {
 
         assert_eq!(0, queue.len());
     }
+
+    #[test]
+    fn test_random_operations_keep_len_and_intervals_consistent() {
+        let mut queue = QueueWithIntervals::new();
+        let mut expected = std::collections::BTreeSet::new();
+
+        let mut seed: i64 = 12345;
+
+        for _ in 0..5000 {
+            seed = (seed * 1103515245 + 12345) % 2147483648;
+            let id = seed % 300;
+
+            match seed % 3 {
+                0 => {
+                    queue.enqueue(id);
+                    expected.insert(id);
+                }
+                1 => {
+                    let result = queue.remove(id);
+                    assert_eq!(expected.remove(&id), result.is_ok());
+                }
+                _ => {
+                    let to_id = id + seed % 7;
+                    queue.enqueue_range(&QueueIndexRange::restore(id, to_id));
+                    expected.extend(id..=to_id);
+                }
+            }
+
+            assert_eq!(expected.len() as i64, queue.len());
+            assert_eq!(expected.contains(&id), queue.has_message(id));
+
+            for window in queue.intervals.windows(2) {
+                assert!(window[0].to_id + 1 < window[1].from_id);
+            }
+        }
+
+        let result: Vec<i64> = queue.into_iter().collect();
+        let expected: Vec<i64> = expected.into_iter().collect();
+        assert_eq!(expected, result);
+    }
+
+    #[test]
+    fn test_dequeue_range() {
+        let mut queue = QueueWithIntervals::new();
+        queue.enqueue_range(&QueueIndexRange::restore(10, 15));
+        queue.enqueue_range(&QueueIndexRange::restore(20, 25));
+
+        let result = queue.dequeue_range(8);
+
+        assert_eq!(2, result.len());
+        assert_eq!(10, result[0].from_id);
+        assert_eq!(15, result[0].to_id);
+        assert_eq!(20, result[1].from_id);
+        assert_eq!(21, result[1].to_id);
+
+        assert_eq!(4, queue.len());
+        assert_eq!(22, queue.peek().unwrap());
+
+        let result = queue.dequeue_range(100);
+
+        assert_eq!(1, result.len());
+        assert_eq!(22, result[0].from_id);
+        assert_eq!(25, result[0].to_id);
+
+        assert_eq!(0, queue.len());
+        assert_eq!(true, queue.dequeue_range(100).is_empty());
+
+        queue.enqueue(30);
+        assert_eq!(1, queue.len());
+        assert_eq!(1, queue.intervals.len());
+    }
+
+    #[test]
+    fn test_remove_range() {
+        let mut queue = QueueWithIntervals::new();
+        queue.enqueue_range(&QueueIndexRange::restore(10, 15));
+        queue.enqueue_range(&QueueIndexRange::restore(20, 25));
+        queue.enqueue_range(&QueueIndexRange::restore(30, 35));
+
+        let removed = queue.remove_range(&QueueIndexRange::restore(13, 31));
+
+        assert_eq!(11, removed.len());
+        assert_eq!(3, removed.intervals.len());
+        assert_eq!(13, removed.intervals[0].from_id);
+        assert_eq!(15, removed.intervals[0].to_id);
+        assert_eq!(20, removed.intervals[1].from_id);
+        assert_eq!(25, removed.intervals[1].to_id);
+        assert_eq!(30, removed.intervals[2].from_id);
+        assert_eq!(31, removed.intervals[2].to_id);
+
+        assert_eq!(7, queue.len());
+        assert_eq!(2, queue.intervals.len());
+        assert_eq!(10, queue.intervals[0].from_id);
+        assert_eq!(12, queue.intervals[0].to_id);
+        assert_eq!(32, queue.intervals[1].from_id);
+        assert_eq!(35, queue.intervals[1].to_id);
+
+        let removed = queue.remove_range(&QueueIndexRange::restore(16, 31));
+        assert_eq!(0, removed.len());
+        assert_eq!(7, queue.len());
+    }
+
+    #[test]
+    fn test_remove_range_inside_interval() {
+        let mut queue = QueueWithIntervals::new();
+        queue.enqueue_range(&QueueIndexRange::restore(10, 20));
+
+        let removed = queue.remove_range(&QueueIndexRange::restore(12, 14));
+
+        assert_eq!(3, removed.len());
+        assert_eq!(8, queue.len());
+        assert_eq!(2, queue.intervals.len());
+        assert_eq!(11, queue.intervals[0].to_id);
+        assert_eq!(15, queue.intervals[1].from_id);
+    }
+
+    #[test]
+    fn test_remove_below_and_above() {
+        let mut queue = QueueWithIntervals::new();
+        queue.enqueue_range(&QueueIndexRange::restore(10, 15));
+        queue.enqueue_range(&QueueIndexRange::restore(20, 25));
+        queue.enqueue_range(&QueueIndexRange::restore(30, 35));
+
+        let removed = queue.remove_below(22);
+        assert_eq!(8, removed.len());
+        assert_eq!(22, queue.get_min_id().unwrap());
+
+        let removed = queue.remove_above(30);
+        assert_eq!(5, removed.len());
+        assert_eq!(30, queue.get_max_id().unwrap());
+
+        assert_eq!(5, queue.len());
+
+        assert_eq!(0, queue.remove_below(i64::MIN).len());
+        assert_eq!(0, queue.remove_above(i64::MAX).len());
+
+        assert_eq!(5, queue.remove_above(i64::MIN).len());
+        assert_eq!(0, queue.len());
+        assert_eq!(1, queue.get_intervals().len());
+        assert_eq!(true, queue.get_intervals()[0].is_empty());
+        assert_eq!(true, queue.validate().is_ok());
+
+        queue.enqueue(40);
+        assert_eq!(1, queue.len());
+        assert_eq!(40, queue.get_min_id().unwrap());
+    }
+
+    #[test]
+    fn test_remove_everything_keeps_empty_interval() {
+        let mut queue = QueueWithIntervals::new();
+        queue.enqueue_range(&QueueIndexRange::restore(10, 15));
+        queue.enqueue_range(&QueueIndexRange::restore(20, 25));
+
+        assert_eq!(
+            12,
+            queue.remove_range(&QueueIndexRange::restore(5, 30)).len()
+        );
+        assert_eq!(0, queue.len());
+        assert_eq!(1, queue.get_intervals().len());
+        assert_eq!(26, queue.get_intervals()[0].from_id);
+        assert_eq!(25, queue.get_intervals()[0].to_id);
+        assert_eq!(true, queue.validate().is_ok());
+
+        let mut queue = QueueWithIntervals::from_single_interval(i64::MAX - 1, i64::MAX);
+        assert_eq!(1, queue.remove_below(i64::MAX).len());
+        assert_eq!(1, queue.remove_above(i64::MIN).len());
+        assert_eq!(0, queue.len());
+        assert_eq!(true, queue.get_intervals()[0].is_empty());
+        assert_eq!(true, queue.validate().is_ok());
+    }
+
+    #[test]
+    fn test_dequeue_range_with_max_count() {
+        let mut queue = QueueWithIntervals::new();
+        queue.enqueue_range(&QueueIndexRange::restore(10, 15));
+        queue.enqueue_range(&QueueIndexRange::restore(20, 25));
+
+        let result = queue.dequeue_range(usize::MAX);
+
+        assert_eq!(2, result.len());
+        assert_eq!(10, result[0].from_id);
+        assert_eq!(25, result[1].to_id);
+        assert_eq!(0, queue.len());
+        assert_eq!(true, queue.validate().is_ok());
+        assert_eq!(0, queue.dequeue_range(usize::MAX).len());
+    }
+
+    #[test]
+    fn test_try_enqueue_already_exists() {
+        let mut queue = QueueWithIntervals::new();
+
+        queue.try_enqueue(5).unwrap();
+        queue.try_enqueue(6).unwrap();
+
+        let result = queue.try_enqueue(5);
+        assert!(matches!(
+            result,
+            Err(QueueWithIntervalsError::AlreadyExists(5))
+        ));
+
+        let result = queue.try_enqueue_range(&QueueIndexRange::restore(1, 6));
+        assert!(matches!(
+            result,
+            Err(QueueWithIntervalsError::AlreadyExists(5))
+        ));
+
+        let result = queue.try_enqueue_range(&QueueIndexRange::restore(8, 7));
+        assert!(matches!(
+            result,
+            Err(QueueWithIntervalsError::EmptyInterval)
+        ));
+
+        queue
+            .try_enqueue_range(&QueueIndexRange::restore(7, 10))
+            .unwrap();
+        assert_eq!(6, queue.len());
+        assert_eq!(1, queue.intervals.len());
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(QueueWithIntervals::new().validate().is_ok());
+
+        let mut queue = QueueWithIntervals::new();
+        queue.enqueue(1);
+        queue.clean();
+        assert!(queue.validate().is_ok());
+
+        let result = QueueWithIntervals::try_restore(vec![
+            QueueIndexRange::restore(1, 5),
+            QueueIndexRange::restore(10, 15),
+        ]);
+        assert!(result.is_ok());
+
+        let result = QueueWithIntervals::try_restore(vec![
+            QueueIndexRange::restore(10, 15),
+            QueueIndexRange::restore(1, 5),
+        ]);
+        assert!(matches!(
+            result,
+            Err(QueueWithIntervalsError::NotSorted { index: 1 })
+        ));
+
+        let result = QueueWithIntervals::try_restore(vec![
+            QueueIndexRange::restore(1, 5),
+            QueueIndexRange::restore(5, 15),
+        ]);
+        assert!(matches!(
+            result,
+            Err(QueueWithIntervalsError::Overlapping { index: 1 })
+        ));
+
+        let result = QueueWithIntervals::try_restore(vec![
+            QueueIndexRange::restore(1, 5),
+            QueueIndexRange::restore(6, 15),
+        ]);
+        assert!(matches!(
+            result,
+            Err(QueueWithIntervalsError::Adjacent { index: 1 })
+        ));
+
+        let result = QueueWithIntervals::try_restore(vec![
+            QueueIndexRange::restore(1, 5),
+            QueueIndexRange::restore(10, 9),
+        ]);
+        assert!(matches!(
+            result,
+            Err(QueueWithIntervalsError::InvalidInterval { index: 1 })
+        ));
+
+        let result = QueueWithIntervals::try_restore(vec![QueueIndexRange::restore(10, 3)]);
+        assert!(matches!(
+            result,
+            Err(QueueWithIntervalsError::InvalidInterval { index: 0 })
+        ));
+
+        let result = QueueWithIntervals::try_restore(vec![QueueIndexRange::new_empty(10)]);
+        assert!(result.is_ok());
+
+        let mut queue = QueueWithIntervals::from_single_interval(1, 5);
+        let result = queue.try_merge_with(&QueueWithIntervals::restore(vec![
+            QueueIndexRange::restore(10, 15),
+            QueueIndexRange::restore(12, 20),
+        ]));
+        assert!(matches!(
+            result,
+            Err(QueueWithIntervalsError::Overlapping { index: 1 })
+        ));
+        assert_eq!(5, queue.len());
+
+        queue
+            .try_merge_with(&QueueWithIntervals::from_single_interval(6, 10))
+            .unwrap();
+        assert_eq!(10, queue.len());
+        assert_eq!(1, queue.get_intervals().len());
+
+        assert!(matches!(
+            queue.try_remove_range(&QueueIndexRange::restore(20, 30)),
+            Err(QueueWithIntervalsError::MessagesNotFound)
+        ));
+        assert!(matches!(
+            queue.try_remove_range(&QueueIndexRange::new_empty(3)),
+            Err(QueueWithIntervalsError::EmptyInterval)
+        ));
+        assert_eq!(10, queue.len());
+
+        let removed = queue
+            .try_remove_range(&QueueIndexRange::restore(3, 4))
+            .unwrap();
+        assert_eq!(2, removed.len());
+        assert_eq!(8, queue.len());
+
+        let mut queue = QueueWithIntervals::from_single_interval(1, 5);
+        let result = queue.try_reset(vec![
+            QueueIndexRange::restore(1, 5),
+            QueueIndexRange::restore(3, 15),
+        ]);
+        assert!(result.is_err());
+        assert_eq!(5, queue.len());
+    }
+
+    #[test]
+    fn test_enqueue_range_at_id_bounds() {
+        let mut queue = QueueWithIntervals::from_single_interval(0, 5);
+
+        queue.enqueue_range(&QueueIndexRange::restore(i64::MIN, i64::MIN + 1));
+        queue.enqueue_range(&QueueIndexRange::restore(i64::MAX - 1, i64::MAX));
+        queue.enqueue(i64::MIN + 2);
+
+        let intervals: Vec<(i64, i64)> = queue
+            .get_intervals()
+            .iter()
+            .map(|item| (item.from_id, item.to_id))
+            .collect();
+
+        assert_eq!(
+            vec![(i64::MIN, i64::MIN + 2), (0, 5), (i64::MAX - 1, i64::MAX)],
+            intervals
+        );
+        assert_eq!(true, queue.validate().is_ok());
+    }
 }
@@ -0,0 +1,293 @@
+use super::{QueueIndexRange, QueueWithIntervals};
+
+impl QueueWithIntervals {
+    fn get_not_empty_intervals(&self) -> impl Iterator<Item = &QueueIndexRange> {
+        self.intervals.iter().filter(|item| !item.is_empty())
+    }
+
+    pub fn union(&self, other: &QueueWithIntervals) -> QueueWithIntervals {
+        let mut result = Vec::with_capacity(self.intervals.len() + other.intervals.len());
+
+        let mut left = self.get_not_empty_intervals().peekable();
+        let mut right = other.get_not_empty_intervals().peekable();
+
+        loop {
+            let next = match (left.peek(), right.peek()) {
+                (Some(l), Some(r)) => {
+                    if l.from_id <= r.from_id {
+                        left.next()
+                    } else {
+                        right.next()
+                    }
+                }
+                (Some(_), None) => left.next(),
+                (None, Some(_)) => right.next(),
+                (None, None) => break,
+            };
+
+            push_and_join(&mut result, next.unwrap());
+        }
+
+        if result.is_empty() {
+            let empty_queue = if self.intervals.is_empty() {
+                other
+            } else {
+                self
+            };
+
+            return QueueWithIntervals::restore(empty_queue.intervals.clone());
+        }
+
+        QueueWithIntervals::restore(result)
+    }
+
+    pub fn intersection(&self, other: &QueueWithIntervals) -> QueueWithIntervals {
+        let mut result = Vec::new();
+
+        let mut left = self.get_not_empty_intervals().peekable();
+        let mut right = other.get_not_empty_intervals().peekable();
+
+        while let (Some(l), Some(r)) = (left.peek(), right.peek()) {
+            let from_id = l.from_id.max(r.from_id);
+            let to_id = l.to_id.min(r.to_id);
+
+            if from_id <= to_id {
+                result.push(QueueIndexRange::restore(from_id, to_id));
+            }
+
+            if l.to_id < r.to_id {
+                left.next();
+            } else {
+                right.next();
+            }
+        }
+
+        QueueWithIntervals::restore(result)
+    }
+
+    pub fn difference(&self, other: &QueueWithIntervals) -> QueueWithIntervals {
+        let mut result = Vec::new();
+
+        let mut right = other.get_not_empty_intervals().peekable();
+
+        for interval in self.get_not_empty_intervals() {
+            let mut from_id = interval.from_id;
+
+            while let Some(r) = right.peek() {
+                if r.to_id < from_id {
+                    right.next();
+                    continue;
+                }
+
+                if r.from_id > interval.to_id {
+                    break;
+                }
+
+                if r.from_id > from_id {
+                    result.push(QueueIndexRange::restore(from_id, r.from_id - 1));
+                }
+
+                from_id = match r.to_id.checked_add(1) {
+                    Some(next_id) => next_id,
+                    None => return QueueWithIntervals::restore(result),
+                };
+
+                if r.to_id > interval.to_id {
+                    break;
+                }
+
+                right.next();
+            }
+
+            if from_id <= interval.to_id {
+                result.push(QueueIndexRange::restore(from_id, interval.to_id));
+            }
+        }
+
+        QueueWithIntervals::restore(result)
+    }
+
+    pub fn symmetric_difference(&self, other: &QueueWithIntervals) -> QueueWithIntervals {
+        self.union(other).difference(&self.intersection(other))
+    }
+
+    pub fn is_subset(&self, other: &QueueWithIntervals) -> bool {
+        let mut right = other.get_not_empty_intervals().peekable();
+
+        for interval in self.get_not_empty_intervals() {
+            while let Some(r) = right.peek() {
+                if r.to_id < interval.from_id {
+                    right.next();
+                } else {
+                    break;
+                }
+            }
+
+            match right.peek() {
+                Some(r) => {
+                    if r.from_id > interval.from_id || r.to_id < interval.to_id {
+                        return false;
+                    }
+                }
+                None => return false,
+            }
+        }
+
+        true
+    }
+
+    pub fn complement_within(&self, range: &QueueIndexRange) -> QueueWithIntervals {
+        QueueWithIntervals::from_single_interval(range.from_id, range.to_id).difference(self)
+    }
+}
+
+fn push_and_join(result: &mut Vec<QueueIndexRange>, range: &QueueIndexRange) {
+    if let Some(last) = result.last_mut() {
+        if range.from_id <= last.to_id.saturating_add(1) {
+            if range.to_id > last.to_id {
+                last.to_id = range.to_id;
+            }
+            return;
+        }
+    }
+
+    result.push(range.clone());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_queue(intervals: &[(i64, i64)]) -> QueueWithIntervals {
+        let mut result = QueueWithIntervals::new();
+
+        for (from_id, to_id) in intervals {
+            result.enqueue_range(&QueueIndexRange::restore(*from_id, *to_id));
+        }
+
+        result
+    }
+
+    fn to_vec(queue: &QueueWithIntervals) -> Vec<(i64, i64)> {
+        queue
+            .intervals
+            .iter()
+            .map(|item| (item.from_id, item.to_id))
+            .collect()
+    }
+
+    #[test]
+    fn test_union() {
+        let left = create_queue(&[(1, 5), (10, 15), (30, 35)]);
+        let right = create_queue(&[(6, 8), (12, 20), (40, 45)]);
+
+        let result = left.union(&right);
+
+        assert_eq!(vec![(1, 8), (10, 20), (30, 35), (40, 45)], to_vec(&result));
+        assert_eq!(31, result.len());
+    }
+
+    #[test]
+    fn test_union_with_empty() {
+        let left = create_queue(&[(1, 5)]);
+        let mut right = QueueWithIntervals::new();
+        right.enqueue(1);
+        right.clean();
+
+        assert_eq!(vec![(1, 5)], to_vec(&left.union(&right)));
+        assert_eq!(vec![(1, 5)], to_vec(&right.union(&left)));
+    }
+
+    #[test]
+    fn test_merge_two_empty_queues_keeps_empty_interval() {
+        let mut left = QueueWithIntervals::restore(vec![QueueIndexRange::restore(101, 100)]);
+        let right = QueueWithIntervals::restore(vec![QueueIndexRange::restore(201, 200)]);
+
+        left.merge_with(&right);
+        assert_eq!(vec![(101, 100)], to_vec(&left));
+        assert_eq!(0, left.len());
+        assert_eq!(true, left.validate().is_ok());
+
+        let mut left = QueueWithIntervals::new();
+        left.merge_with(&right);
+        assert_eq!(vec![(201, 200)], to_vec(&left));
+
+        left.enqueue(300);
+        assert_eq!(vec![(300, 300)], to_vec(&left));
+    }
+
+    #[test]
+    fn test_intersection() {
+        let left = create_queue(&[(1, 5), (10, 15), (30, 35)]);
+        let right = create_queue(&[(3, 12), (14, 31), (40, 45)]);
+
+        let result = left.intersection(&right);
+
+        assert_eq!(vec![(3, 5), (10, 12), (14, 15), (30, 31)], to_vec(&result));
+        assert_eq!(10, result.len());
+    }
+
+    #[test]
+    fn test_difference() {
+        let left = create_queue(&[(1, 10), (20, 30)]);
+        let right = create_queue(&[(3, 4), (8, 22), (25, 25), (29, 40)]);
+
+        let result = left.difference(&right);
+
+        assert_eq!(vec![(1, 2), (5, 7), (23, 24), (26, 28)], to_vec(&result));
+        assert_eq!(10, result.len());
+    }
+
+    #[test]
+    fn test_operations_with_intervals_ending_at_max_id() {
+        let left = create_queue(&[(1, 5), (10, 20)]);
+        let right = create_queue(&[(6, i64::MAX)]);
+
+        assert_eq!(vec![(1, i64::MAX)], to_vec(&left.union(&right)));
+        assert_eq!(vec![(1, 5)], to_vec(&left.difference(&right)));
+        assert_eq!(vec![(10, 20)], to_vec(&left.intersection(&right)));
+
+        let left = create_queue(&[(10, 20), (30, i64::MAX)]);
+        let right = create_queue(&[(15, 40)]);
+
+        assert_eq!(vec![(10, i64::MAX)], to_vec(&left.union(&right)));
+        assert_eq!(
+            vec![(10, 14), (41, i64::MAX)],
+            to_vec(&left.difference(&right))
+        );
+        assert_eq!(
+            vec![(1, 9), (21, 29)],
+            to_vec(&left.complement_within(&QueueIndexRange::restore(1, i64::MAX)))
+        );
+    }
+
+    #[test]
+    fn test_symmetric_difference() {
+        let left = create_queue(&[(1, 10)]);
+        let right = create_queue(&[(5, 15)]);
+
+        let result = left.symmetric_difference(&right);
+
+        assert_eq!(vec![(1, 4), (11, 15)], to_vec(&result));
+    }
+
+    #[test]
+    fn test_is_subset() {
+        let queue = create_queue(&[(1, 10), (20, 30)]);
+
+        assert!(create_queue(&[(2, 3), (20, 30)]).is_subset(&queue));
+        assert!(QueueWithIntervals::new().is_subset(&queue));
+        assert!(!create_queue(&[(2, 3), (19, 30)]).is_subset(&queue));
+        assert!(!create_queue(&[(9, 21)]).is_subset(&queue));
+        assert!(!create_queue(&[(31, 31)]).is_subset(&queue));
+    }
+
+    #[test]
+    fn test_complement_within() {
+        let queue = create_queue(&[(3, 5), (8, 8), (20, 30)]);
+
+        let result = queue.complement_within(&QueueIndexRange::restore(1, 10));
+
+        assert_eq!(vec![(1, 2), (6, 7), (9, 10)], to_vec(&result));
+    }
+}
@@ -0,0 +1,85 @@
+use std::sync::Arc;
+
+use crate::{publisher::MessageToPublish, MySbMessage, MyServiceBusPublisherClient, PublishError};
+
+pub const DEAD_LETTER_ORIGINAL_TOPIC_HEADER: &str = "dead-letter-original-topic";
+pub const DEAD_LETTER_ORIGINAL_QUEUE_HEADER: &str = "dead-letter-original-queue";
+pub const DEAD_LETTER_ATTEMPT_HEADER: &str = "dead-letter-attempt";
+pub const DEAD_LETTER_LAST_ERROR_HEADER: &str = "dead-letter-last-error";
+
+#[derive(Clone)]
+pub struct DeadLetterPolicy {
+    pub max_attempts: i32,
+    pub topic_id: String,
+    pub client: Arc<dyn MyServiceBusPublisherClient + Send + Sync + 'static>,
+}
+
+impl DeadLetterPolicy {
+    pub fn new(
+        max_attempts: i32,
+        topic_id: String,
+        client: Arc<dyn MyServiceBusPublisherClient + Send + Sync + 'static>,
+    ) -> Self {
+        Self {
+            max_attempts,
+            topic_id,
+            client,
+        }
+    }
+
+    pub fn is_exceeded(&self, message: &MySbMessage) -> bool {
+        message.attempt_no >= self.max_attempts
+    }
+
+    pub async fn publish(
+        &self,
+        topic_id: &str,
+        queue_id: &str,
+        messages: &[(&MySbMessage, Option<String>)],
+    ) -> Result<(), PublishError> {
+        let to_publish: Vec<MessageToPublish> = messages
+            .iter()
+            .map(|(message, last_error)| {
+                let mut headers = message.headers.clone().unwrap_or_default();
+
+                headers.insert(
+                    DEAD_LETTER_ORIGINAL_TOPIC_HEADER.to_string(),
+                    topic_id.to_string(),
+                );
+                headers.insert(
+                    DEAD_LETTER_ORIGINAL_QUEUE_HEADER.to_string(),
+                    queue_id.to_string(),
+                );
+                headers.insert(
+                    DEAD_LETTER_ATTEMPT_HEADER.to_string(),
+                    message.attempt_no.to_string(),
+                );
+
+                if let Some(last_error) = last_error {
+                    headers.insert(
+                        DEAD_LETTER_LAST_ERROR_HEADER.to_string(),
+                        last_error.to_string(),
+                    );
+                }
+
+                MessageToPublish {
+                    headers: Some(headers),
+                    content: message.content.clone(),
+                }
+            })
+            .collect();
+
+        self.client
+            .publish_messages(&self.topic_id, &to_publish, false)
+            .await
+    }
+}
+
+impl std::fmt::Debug for DeadLetterPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DeadLetterPolicy")
+            .field("max_attempts", &self.max_attempts)
+            .field("topic_id", &self.topic_id)
+            .finish()
+    }
+}
@@ -6,12 +6,19 @@ use crate::MessageId;
 
 use super::MySbMessageDeserializer;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageHandleResult {
+    Ack,
+    Nack,
+}
+
 pub struct MySbDeliveredMessage<TMessageModel: MySbMessageDeserializer<Item = TMessageModel>> {
     pub id: MessageId,
     pub attempt_no: i32,
     pub headers: Option<HashMap<String, String>>,
     pub raw: Vec<u8>,
     pub content: Option<TMessageModel>,
+    pub handle_result: Option<MessageHandleResult>,
     #[cfg(feature = "with-telemetry")]
     pub my_telemetry_ctx: Option<MyTelemetryContext>,
     #[cfg(feature = "with-telemetry")]
@@ -21,6 +28,27 @@ pub struct MySbDeliveredMessage<TMessageModel: MySbMessageDeserializer<Item = TM
 impl<TMessageModel: MySbMessageDeserializer<Item = TMessageModel>>
     MySbDeliveredMessage<TMessageModel>
 {
+    pub fn new(
+        id: MessageId,
+        attempt_no: i32,
+        headers: Option<HashMap<String, String>>,
+        raw: Vec<u8>,
+        content: TMessageModel,
+    ) -> Self {
+        Self {
+            id,
+            attempt_no,
+            headers,
+            raw,
+            content: Some(content),
+            handle_result: None,
+            #[cfg(feature = "with-telemetry")]
+            my_telemetry_ctx: None,
+            #[cfg(feature = "with-telemetry")]
+            event_tracker: None,
+        }
+    }
+
     pub fn take_message(&mut self) -> TMessageModel {
         let result = self.content.take();
         if result.is_none() {
@@ -37,6 +65,22 @@ impl<TMessageModel: MySbMessageDeserializer<Item = TMessageModel>>
         panic!("Message was already taken");
     }
 
+    pub fn ack(&mut self) {
+        self.handle_result = Some(MessageHandleResult::Ack);
+    }
+
+    pub fn nack(&mut self) {
+        self.handle_result = Some(MessageHandleResult::Nack);
+    }
+
+    pub fn get_handle_result(&self) -> Option<MessageHandleResult> {
+        self.handle_result
+    }
+
+    pub(crate) fn set_handle_result(&mut self, handle_result: MessageHandleResult) {
+        self.handle_result = Some(handle_result);
+    }
+
     #[cfg(feature = "with-telemetry")]
     pub fn init_telemetry_context(&mut self, topic_id: &str, queue_id: &str) {
         use crate::MY_TELEMETRY_HEADER;
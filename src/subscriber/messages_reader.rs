@@ -5,19 +5,23 @@ use std::{
 
 use crate::{
     queue_with_intervals::QueueWithIntervals,
-    subscriber::{MySbDeliveredMessage, MySbMessageDeserializer},
+    subscriber::{MessageHandleResult, MySbDeliveredMessage, MySbMessageDeserializer},
+    MessageId,
 };
 
-use super::SubscriberData;
+use super::{DispatchedMessages, SubscriberData};
 
 pub struct MessagesReader<TMessageModel: MySbMessageDeserializer<Item = TMessageModel>> {
     pub data: Arc<SubscriberData>,
     total_messages_amount: i64,
     messages: Option<VecDeque<MySbDeliveredMessage<TMessageModel>>>,
     pub confirmation_id: i64,
+    message_ids: QueueWithIntervals,
     delivered: QueueWithIntervals,
+    handle_results: HashMap<i64, MessageHandleResult>,
     connection_id: i32,
     current_message: Option<MySbDeliveredMessage<TMessageModel>>,
+    pub(crate) dispatched: Option<DispatchedMessages>,
 }
 
 impl<TMessageModel: MySbMessageDeserializer<Item = TMessageModel>> MessagesReader<TMessageModel> {
@@ -28,33 +32,125 @@ impl<TMessageModel: MySbMessageDeserializer<Item = TMessageModel>> MessagesReade
         connection_id: i32,
     ) -> Self {
         let total_messages_amount = messages.len() as i64;
+
+        let mut message_ids = QueueWithIntervals::new();
+        for msg in &messages {
+            message_ids.enqueue(msg.id.get_value());
+        }
+
+        data.reader_is_created();
+
         Self {
             data,
             messages: Some(messages),
             confirmation_id,
+            message_ids,
             delivered: QueueWithIntervals::new(),
+            handle_results: HashMap::new(),
             total_messages_amount,
             connection_id,
             current_message: None,
+            dispatched: None,
+        }
+    }
+
+    fn set_handle_result(&mut self, id: i64, handle_result: MessageHandleResult) {
+        match handle_result {
+            MessageHandleResult::Ack => {
+                if !self.delivered.has_message(id) {
+                    self.delivered.enqueue(id);
+                }
+            }
+            MessageHandleResult::Nack => {
+                let _ = self.delivered.remove(id);
+            }
+        }
+    }
+
+    fn apply_handle_result(&mut self, id: MessageId, handle_result: MessageHandleResult) -> bool {
+        if let Some(current_message) = self.current_message.as_mut() {
+            if current_message.id == id {
+                current_message.set_handle_result(handle_result);
+                return true;
+            }
+        }
+
+        if !self.message_ids.has_message(id.get_value()) {
+            return false;
+        }
+
+        self.handle_results.insert(id.get_value(), handle_result);
+        self.set_handle_result(id.get_value(), handle_result);
+        true
+    }
+
+    fn complete_current_message(&mut self, default_result: Option<MessageHandleResult>) {
+        let message = match self.current_message.take() {
+            Some(message) => message,
+            None => return,
+        };
+
+        let id = message.id.get_value();
+
+        let handle_result = message
+            .get_handle_result()
+            .or_else(|| self.handle_results.get(&id).copied())
+            .or(default_result);
+
+        if let Some(handle_result) = handle_result {
+            self.set_handle_result(id, handle_result);
         }
     }
 
-    fn handled_ok(&mut self, msg: &MySbDeliveredMessage<TMessageModel>) {
-        self.delivered.enqueue(msg.id.get_value());
+    pub fn ack(&mut self, id: MessageId) -> bool {
+        self.apply_handle_result(id, MessageHandleResult::Ack)
+    }
+
+    pub fn nack(&mut self, id: MessageId) -> bool {
+        self.apply_handle_result(id, MessageHandleResult::Nack)
     }
 
     pub fn get_next_message<'s>(
         &'s mut self,
     ) -> Option<&'s mut MySbDeliveredMessage<TMessageModel>> {
-        if let Some(message) = self.current_message.take() {
-            self.handled_ok(&message);
-        }
+        self.complete_current_message(Some(MessageHandleResult::Ack));
 
         let messages = self.messages.as_mut()?;
         self.current_message = Some(messages.pop_front()?);
         self.current_message.as_mut()
     }
 
+    pub(crate) fn nack_current_message(&mut self) -> Option<MessageId> {
+        let current_message = self.current_message.as_mut()?;
+        current_message.nack();
+        Some(current_message.id)
+    }
+
+    pub(crate) fn add_handled_message(&mut self, id: i64, delivered: bool) {
+        if self.message_ids.has_message(id) {
+            return;
+        }
+
+        self.message_ids.enqueue(id);
+        self.total_messages_amount += 1;
+
+        if delivered {
+            self.delivered.enqueue(id);
+        }
+    }
+
+    pub(crate) fn get_not_delivered_ids(&self) -> Vec<i64> {
+        let mut result = Vec::new();
+
+        for id in &self.message_ids {
+            if !self.delivered.has_message(id) {
+                result.push(id);
+            }
+        }
+
+        result
+    }
+
     pub fn get_all(&mut self) -> Option<VecDeque<MySbDeliveredMessage<TMessageModel>>> {
         self.messages.take()
     }
@@ -64,6 +160,10 @@ impl<TMessageModel: MySbMessageDeserializer<Item = TMessageModel>> Drop
     for MessagesReader<TMessageModel>
 {
     fn drop(&mut self) {
+        self.complete_current_message(None);
+
+        self.data.messages_are_delivered(&self.delivered);
+
         if self.delivered.len() == self.total_messages_amount {
             self.data.client.confirm_delivery(
                 self.data.topic_id.as_str(),
@@ -134,5 +234,139 @@ impl<TMessageModel: MySbMessageDeserializer<Item = TMessageModel>> Drop
                 self.delivered.get_snapshot(),
             );
         };
+
+        self.data.reader_is_released();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_utils::*;
+    use super::*;
+
+    fn create_test_reader(ids: &[i64]) -> (Arc<TestClient>, MessagesReader<TestMessage>) {
+        let client = TestClient::new();
+        let data = create_subscriber_data(&client, &TestLogger::new(), Default::default());
+        let reader = create_reader(&data, ids, 1);
+        (client, reader)
+    }
+
+    #[test]
+    fn test_iterated_messages_are_acked_by_default() {
+        let (client, mut reader) = create_test_reader(&[1, 2, 3]);
+
+        while reader.get_next_message().is_some() {}
+
+        drop(reader);
+
+        assert_eq!(
+            vec![TestConfirmation::All {
+                confirmation_id: 1,
+                delivered: true
+            }],
+            client.get_confirmations()
+        );
+    }
+
+    #[test]
+    fn test_nack_current_message() {
+        let (client, mut reader) = create_test_reader(&[1, 2, 3]);
+
+        while let Some(msg) = reader.get_next_message() {
+            if msg.id.get_value() == 2 {
+                msg.nack();
+            }
+        }
+
+        drop(reader);
+
+        assert_eq!(
+            vec![TestConfirmation::Some {
+                confirmation_id: 1,
+                ok_messages: vec![(1, 1), (3, 3)]
+            }],
+            client.get_confirmations()
+        );
+    }
+
+    #[test]
+    fn test_nack_by_id_before_iteration() {
+        let (client, mut reader) = create_test_reader(&[1, 2, 3]);
+
+        assert_eq!(true, reader.nack(MessageId::new(3)));
+
+        while reader.get_next_message().is_some() {}
+
+        drop(reader);
+
+        assert_eq!(
+            vec![TestConfirmation::Some {
+                confirmation_id: 1,
+                ok_messages: vec![(1, 2)]
+            }],
+            client.get_confirmations()
+        );
+    }
+
+    #[test]
+    fn test_ack_and_nack_by_id_out_of_order() {
+        let (client, mut reader) = create_test_reader(&[1, 2, 3, 4]);
+
+        let messages = reader.get_all().unwrap();
+
+        assert_eq!(true, reader.ack(MessageId::new(4)));
+        assert_eq!(true, reader.nack(MessageId::new(1)));
+        assert_eq!(true, reader.ack(MessageId::new(2)));
+        assert_eq!(false, reader.ack(MessageId::new(5)));
+
+        drop(messages);
+        drop(reader);
+
+        assert_eq!(
+            vec![TestConfirmation::Some {
+                confirmation_id: 1,
+                ok_messages: vec![(2, 2), (4, 4)]
+            }],
+            client.get_confirmations()
+        );
+    }
+
+    #[test]
+    fn test_message_result_overrides_reader_result() {
+        let (client, mut reader) = create_test_reader(&[1, 2]);
+
+        reader.nack(MessageId::new(2));
+
+        while let Some(msg) = reader.get_next_message() {
+            msg.ack();
+        }
+
+        drop(reader);
+
+        assert_eq!(
+            vec![TestConfirmation::All {
+                confirmation_id: 1,
+                delivered: true
+            }],
+            client.get_confirmations()
+        );
+    }
+
+    #[test]
+    fn test_not_iterated_messages_are_not_delivered() {
+        let (client, mut reader) = create_test_reader(&[1, 2, 3]);
+
+        reader.get_next_message();
+        reader.get_next_message();
+
+        drop(reader);
+
+        assert_eq!(
+            vec![TestConfirmation::Some {
+                confirmation_id: 1,
+                ok_messages: vec![(1, 1)]
+            }],
+            client.get_confirmations()
+        );
     }
 }
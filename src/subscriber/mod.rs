@@ -1,12 +1,26 @@
+mod dead_letter;
 mod delivered_message;
 mod deserializer;
 mod messages_reader;
+mod partitioned_handler;
+mod poison_message;
 mod queue_type;
+mod settings;
+mod single_message_handler;
 mod subscriber;
 mod subscriber_callback;
+mod subscriber_stream;
+#[cfg(test)]
+mod test_utils;
+pub use dead_letter::*;
 pub use delivered_message::*;
 pub use deserializer::*;
 pub use messages_reader::*;
+pub use partitioned_handler::*;
+pub use poison_message::*;
 pub use queue_type::*;
+pub use settings::*;
+pub use single_message_handler::*;
 pub use subscriber::*;
 pub use subscriber_callback::*;
+pub use subscriber_stream::*;
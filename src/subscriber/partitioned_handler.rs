@@ -0,0 +1,389 @@
+use std::{
+    collections::HashMap,
+    panic::AssertUnwindSafe,
+    sync::{Arc, Mutex},
+};
+
+use futures::FutureExt;
+use tokio::{
+    sync::{mpsc, oneshot, Semaphore},
+    task::JoinSet,
+};
+
+use crate::MessageId;
+
+use super::{
+    get_panic_message, write_handle_error, MessageHandleResult, MessagesReader,
+    MySbDeliveredMessage, MySbMessageDeserializer, MySbSubscriberHandleError, SingleMessageHandler,
+    SubscriberCallback, SubscriberData,
+};
+
+pub trait MessagePartitionKey<TMessageModel: MySbMessageDeserializer<Item = TMessageModel>> {
+    fn get_partition_key(&self, msg: &MySbDeliveredMessage<TMessageModel>) -> String;
+}
+
+impl<TMessageModel, TFn> MessagePartitionKey<TMessageModel> for TFn
+where
+    TMessageModel: MySbMessageDeserializer<Item = TMessageModel>,
+    TFn: Fn(&MySbDeliveredMessage<TMessageModel>) -> String,
+{
+    fn get_partition_key(&self, msg: &MySbDeliveredMessage<TMessageModel>) -> String {
+        self(msg)
+    }
+}
+
+struct LaneMessage<TMessageModel: MySbMessageDeserializer<Item = TMessageModel>> {
+    data: Arc<SubscriberData>,
+    confirmation_id: i64,
+    message: MySbDeliveredMessage<TMessageModel>,
+    result: oneshot::Sender<bool>,
+}
+
+struct Lane<TMessageModel: MySbMessageDeserializer<Item = TMessageModel>> {
+    sender: mpsc::UnboundedSender<LaneMessage<TMessageModel>>,
+    pending: usize,
+}
+
+type Lanes<TMessageModel> = Arc<Mutex<HashMap<String, Lane<TMessageModel>>>>;
+
+pub(crate) trait LaneDispatcher<TMessageModel: MySbMessageDeserializer<Item = TMessageModel>> {
+    fn dispatch_messages(&self, messages_reader: &mut MessagesReader<TMessageModel>);
+    fn abort_lanes(&self);
+}
+
+pub(crate) type DispatchedMessages = Vec<(MessageId, oneshot::Receiver<bool>)>;
+
+pub struct PartitionedMessageHandlerCallback<
+    TMessageModel: MySbMessageDeserializer<Item = TMessageModel> + Send + Sync + 'static,
+> {
+    pub handler: Arc<dyn SingleMessageHandler<TMessageModel> + Send + Sync + 'static>,
+    pub partition_key: Arc<dyn MessagePartitionKey<TMessageModel> + Send + Sync + 'static>,
+    pub max_parallel_partitions: usize,
+    lanes: Lanes<TMessageModel>,
+    partitions_limit: Arc<Semaphore>,
+    lane_workers: Mutex<JoinSet<()>>,
+}
+
+impl<TMessageModel: MySbMessageDeserializer<Item = TMessageModel> + Send + Sync + 'static>
+    PartitionedMessageHandlerCallback<TMessageModel>
+{
+    pub fn new(
+        handler: Arc<dyn SingleMessageHandler<TMessageModel> + Send + Sync + 'static>,
+        partition_key: Arc<dyn MessagePartitionKey<TMessageModel> + Send + Sync + 'static>,
+        max_parallel_partitions: usize,
+    ) -> Self {
+        Self {
+            handler,
+            partition_key,
+            max_parallel_partitions,
+            lanes: Arc::new(Mutex::new(HashMap::new())),
+            partitions_limit: Arc::new(Semaphore::new(max_parallel_partitions.max(1))),
+            lane_workers: Mutex::new(JoinSet::new()),
+        }
+    }
+
+    fn dispatch(&self, messages_reader: &mut MessagesReader<TMessageModel>) -> DispatchedMessages {
+        let messages = match messages_reader.get_all() {
+            Some(messages) => messages,
+            None => return Vec::new(),
+        };
+
+        let mut result = Vec::with_capacity(messages.len());
+
+        let mut lanes = self.lanes.lock().unwrap();
+
+        for message in messages {
+            let key = self.partition_key.get_partition_key(&message);
+
+            let lane = lanes.entry(key.clone()).or_insert_with(|| {
+                let (sender, receiver) = mpsc::unbounded_channel();
+
+                let mut lane_workers = self.lane_workers.lock().unwrap();
+                while lane_workers.try_join_next().is_some() {}
+
+                lane_workers.spawn(lane_worker(
+                    key,
+                    self.handler.clone(),
+                    self.lanes.clone(),
+                    self.partitions_limit.clone(),
+                    receiver,
+                ));
+
+                Lane { sender, pending: 0 }
+            });
+
+            let (sender, receiver) = oneshot::channel();
+            result.push((message.id, receiver));
+
+            lane.pending += 1;
+            let _ = lane.sender.send(LaneMessage {
+                data: messages_reader.data.clone(),
+                confirmation_id: messages_reader.confirmation_id,
+                message,
+                result: sender,
+            });
+        }
+
+        result
+    }
+}
+
+impl<TMessageModel: MySbMessageDeserializer<Item = TMessageModel> + Send + Sync + 'static>
+    LaneDispatcher<TMessageModel> for PartitionedMessageHandlerCallback<TMessageModel>
+{
+    fn dispatch_messages(&self, messages_reader: &mut MessagesReader<TMessageModel>) {
+        messages_reader.dispatched = Some(self.dispatch(messages_reader));
+    }
+
+    fn abort_lanes(&self) {
+        self.lane_workers.lock().unwrap().abort_all();
+        self.lanes.lock().unwrap().clear();
+    }
+}
+
+async fn lane_worker<
+    TMessageModel: MySbMessageDeserializer<Item = TMessageModel> + Send + Sync + 'static,
+>(
+    key: String,
+    handler: Arc<dyn SingleMessageHandler<TMessageModel> + Send + Sync + 'static>,
+    lanes: Lanes<TMessageModel>,
+    partitions_limit: Arc<Semaphore>,
+    mut receiver: mpsc::UnboundedReceiver<LaneMessage<TMessageModel>>,
+) {
+    let mut has_failed = false;
+
+    while let Some(lane_message) = receiver.recv().await {
+        let LaneMessage {
+            data,
+            confirmation_id,
+            mut message,
+            result,
+        } = lane_message;
+
+        if !has_failed {
+            let _permit = partitions_limit.acquire().await;
+            has_failed = !handle_message(&handler, &data, confirmation_id, &mut message).await;
+        }
+
+        let _ = result.send(!has_failed);
+
+        let mut lanes = lanes.lock().unwrap();
+
+        if let Some(lane) = lanes.get_mut(&key) {
+            lane.pending -= 1;
+
+            if lane.pending == 0 {
+                lanes.remove(&key);
+                return;
+            }
+        }
+    }
+}
+
+async fn handle_message<
+    TMessageModel: MySbMessageDeserializer<Item = TMessageModel> + Send + Sync + 'static,
+>(
+    handler: &Arc<dyn SingleMessageHandler<TMessageModel> + Send + Sync + 'static>,
+    data: &SubscriberData,
+    confirmation_id: i64,
+    message: &mut MySbDeliveredMessage<TMessageModel>,
+) -> bool {
+    let result = AssertUnwindSafe(handler.handle(message))
+        .catch_unwind()
+        .await;
+
+    let err = match result {
+        Ok(Ok(_)) => return message.get_handle_result() != Some(MessageHandleResult::Nack),
+        Ok(Err(err)) => err,
+        Err(panic) => MySbSubscriberHandleError {
+            msg: format!(
+                "Handler panicked. Panic: {}",
+                get_panic_message(panic.as_ref())
+            ),
+        },
+    };
+
+    write_handle_error(data, confirmation_id, message.id.get_value(), err);
+    false
+}
+
+#[async_trait::async_trait]
+impl<TMessageModel: MySbMessageDeserializer<Item = TMessageModel> + Send + Sync + 'static>
+    SubscriberCallback<TMessageModel> for PartitionedMessageHandlerCallback<TMessageModel>
+{
+    async fn handle_messages(
+        &self,
+        messages_reader: &mut MessagesReader<TMessageModel>,
+    ) -> Result<(), MySbSubscriberHandleError> {
+        let dispatched = match messages_reader.dispatched.take() {
+            Some(dispatched) => dispatched,
+            None => self.dispatch(messages_reader),
+        };
+
+        for (id, receiver) in dispatched {
+            if receiver.await == Ok(true) {
+                messages_reader.ack(id);
+            } else {
+                messages_reader.nack(id);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::super::test_utils::*;
+    use super::*;
+    use crate::{
+        subscriber::{Subscriber, SubscriberSettings, TopicQueueType},
+        MyServiceBusSubscriberClientCallback,
+    };
+
+    struct BlockingHandler {
+        blocked_id: i64,
+        gate: Semaphore,
+        handled: Mutex<Vec<i64>>,
+    }
+
+    #[async_trait::async_trait]
+    impl SingleMessageHandler<TestMessage> for BlockingHandler {
+        async fn handle(
+            &self,
+            msg: &mut MySbDeliveredMessage<TestMessage>,
+        ) -> Result<(), MySbSubscriberHandleError> {
+            if msg.id.get_value() == self.blocked_id {
+                self.gate.acquire().await.unwrap().forget();
+            }
+
+            self.handled.lock().unwrap().push(msg.id.get_value());
+
+            Ok(())
+        }
+    }
+
+    fn create_partitioned_subscriber(
+        client: &Arc<TestClient>,
+        handler: Arc<BlockingHandler>,
+        settings: SubscriberSettings,
+    ) -> (
+        Arc<Subscriber<TestMessage>>,
+        Arc<PartitionedMessageHandlerCallback<TestMessage>>,
+    ) {
+        let callback = Arc::new(PartitionedMessageHandlerCallback::new(
+            handler,
+            Arc::new(|msg: &MySbDeliveredMessage<TestMessage>| msg.raw[0].to_string()),
+            4,
+        ));
+
+        let subscriber = Subscriber::new_partitioned_with_settings(
+            "test-topic".into(),
+            "test-queue".into(),
+            TopicQueueType::Permanent,
+            callback.clone(),
+            TestLogger::new(),
+            client.clone(),
+            settings,
+        );
+
+        (Arc::new(subscriber), callback)
+    }
+
+    async fn wait_until_handled(handler: &BlockingHandler, amount: usize) -> Vec<i64> {
+        for _ in 0..1000 {
+            let handled = handler.handled.lock().unwrap().clone();
+
+            if handled.len() >= amount {
+                return handled;
+            }
+
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+
+        panic!("Expected {} handled messages", amount);
+    }
+
+    #[tokio::test]
+    async fn test_key_order_is_kept_across_overlapping_deliveries() {
+        let client = TestClient::new();
+
+        let handler = Arc::new(BlockingHandler {
+            blocked_id: 1,
+            gate: Semaphore::new(0),
+            handled: Mutex::new(Vec::new()),
+        });
+
+        let (subscriber, _) =
+            create_partitioned_subscriber(&client, handler.clone(), Default::default());
+
+        subscriber
+            .new_events(vec![create_message(1, 0, 10)], 1, 0)
+            .await;
+
+        subscriber
+            .new_events(
+                vec![create_message(2, 0, 10), create_message(3, 0, 20)],
+                2,
+                0,
+            )
+            .await;
+
+        assert_eq!(vec![3], wait_until_handled(&handler, 1).await);
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(vec![3], *handler.handled.lock().unwrap());
+        assert_eq!(0, client.get_confirmations().len());
+
+        handler.gate.add_permits(1);
+
+        assert_eq!(vec![3, 1, 2], wait_until_handled(&handler, 3).await);
+
+        let confirmations = client.wait_for_confirmations(2).await;
+
+        for confirmation_id in [1, 2] {
+            assert_eq!(
+                true,
+                confirmations.contains(&TestConfirmation::All {
+                    confirmation_id,
+                    delivered: true
+                })
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_aborts_lane_workers() {
+        let client = TestClient::new();
+
+        let handler = Arc::new(BlockingHandler {
+            blocked_id: 1,
+            gate: Semaphore::new(0),
+            handled: Mutex::new(Vec::new()),
+        });
+
+        let (subscriber, callback) =
+            create_partitioned_subscriber(&client, handler.clone(), Default::default());
+
+        subscriber
+            .new_events(
+                vec![create_message(1, 0, 10), create_message(2, 0, 10)],
+                1,
+                0,
+            )
+            .await;
+
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert_eq!(1, callback.lanes.lock().unwrap().len());
+
+        assert_eq!(1, subscriber.shutdown(Duration::from_millis(50)).await);
+
+        assert_eq!(0, callback.lanes.lock().unwrap().len());
+
+        handler.gate.add_permits(1);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(true, handler.handled.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,110 @@
+use std::sync::{Arc, Mutex};
+
+use crate::MySbMessage;
+
+use super::DeadLetterPolicy;
+
+#[async_trait::async_trait]
+pub trait PoisonMessageStore {
+    async fn store(
+        &self,
+        topic_id: &str,
+        queue_id: &str,
+        message: &MySbMessage,
+        error: &str,
+    ) -> Result<(), String>;
+}
+
+pub const DEFAULT_POISON_MESSAGE_MAX_ATTEMPTS: i32 = 10;
+
+#[derive(Clone)]
+pub enum PoisonMessageStrategy {
+    Reject { max_attempts: i32 },
+    Quarantine(Arc<dyn PoisonMessageStore + Send + Sync + 'static>),
+    DeadLetter(DeadLetterPolicy),
+}
+
+impl Default for PoisonMessageStrategy {
+    fn default() -> Self {
+        Self::Reject {
+            max_attempts: DEFAULT_POISON_MESSAGE_MAX_ATTEMPTS,
+        }
+    }
+}
+
+impl std::fmt::Debug for PoisonMessageStrategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Reject { max_attempts } => f
+                .debug_struct("Reject")
+                .field("max_attempts", max_attempts)
+                .finish(),
+            Self::Quarantine(_) => write!(f, "Quarantine"),
+            Self::DeadLetter(dead_letter) => {
+                f.debug_tuple("DeadLetter").field(dead_letter).finish()
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct QuarantinedMessage {
+    pub topic_id: String,
+    pub queue_id: String,
+    pub message: MySbMessage,
+    pub error: String,
+}
+
+pub struct InMemoryPoisonMessageStore {
+    messages: Mutex<Vec<QuarantinedMessage>>,
+}
+
+impl InMemoryPoisonMessageStore {
+    pub fn new() -> Self {
+        Self {
+            messages: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn get_messages(&self) -> Vec<QuarantinedMessage> {
+        self.messages.lock().unwrap().clone()
+    }
+
+    pub fn take_messages(&self) -> Vec<QuarantinedMessage> {
+        std::mem::take(&mut *self.messages.lock().unwrap())
+    }
+
+    pub fn len(&self) -> usize {
+        self.messages.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Default for InMemoryPoisonMessageStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait::async_trait]
+impl PoisonMessageStore for InMemoryPoisonMessageStore {
+    async fn store(
+        &self,
+        topic_id: &str,
+        queue_id: &str,
+        message: &MySbMessage,
+        error: &str,
+    ) -> Result<(), String> {
+        self.messages.lock().unwrap().push(QuarantinedMessage {
+            topic_id: topic_id.to_string(),
+            queue_id: queue_id.to_string(),
+            message: message.clone(),
+            error: error.to_string(),
+        });
+
+        Ok(())
+    }
+}
@@ -0,0 +1,51 @@
+use std::{sync::Arc, time::Duration};
+
+use tokio::sync::Semaphore;
+
+use super::{DeadLetterPolicy, PoisonMessageStrategy};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubscriberProcessingMode {
+    Concurrent,
+    Sequential,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubscriberPausePolicy {
+    Reject,
+    Buffer { max_deliveries: usize },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubscriberState {
+    Active,
+    Paused,
+    ShuttingDown,
+}
+
+#[derive(Debug, Clone)]
+pub struct SubscriberSettings {
+    pub max_concurrent_handlers: Option<usize>,
+    pub shared_handlers_limit: Option<Arc<Semaphore>>,
+    pub processing_mode: SubscriberProcessingMode,
+    pub dead_letter: Option<DeadLetterPolicy>,
+    pub poison_message_strategy: PoisonMessageStrategy,
+    pub handler_timeout: Option<Duration>,
+    pub pause_policy: SubscriberPausePolicy,
+    pub max_pending_deliveries: usize,
+}
+
+impl Default for SubscriberSettings {
+    fn default() -> Self {
+        Self {
+            max_concurrent_handlers: None,
+            shared_handlers_limit: None,
+            processing_mode: SubscriberProcessingMode::Concurrent,
+            dead_letter: None,
+            poison_message_strategy: PoisonMessageStrategy::default(),
+            handler_timeout: None,
+            pause_policy: SubscriberPausePolicy::Reject,
+            max_pending_deliveries: 16,
+        }
+    }
+}
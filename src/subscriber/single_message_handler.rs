@@ -0,0 +1,224 @@
+use std::{collections::HashMap, sync::Arc};
+
+use futures::StreamExt;
+
+use super::{
+    MessageHandleResult, MessagesReader, MySbDeliveredMessage, MySbMessageDeserializer,
+    MySbSubscriberHandleError, SubscriberCallback, SubscriberData,
+};
+
+#[async_trait::async_trait]
+pub trait SingleMessageHandler<
+    TMessageModel: MySbMessageDeserializer<Item = TMessageModel> + Send + Sync + 'static,
+>
+{
+    async fn handle(
+        &self,
+        msg: &mut MySbDeliveredMessage<TMessageModel>,
+    ) -> Result<(), MySbSubscriberHandleError>;
+}
+
+pub struct SingleMessageHandlerCallback<
+    TMessageModel: MySbMessageDeserializer<Item = TMessageModel> + Send + Sync + 'static,
+> {
+    pub handler: Arc<dyn SingleMessageHandler<TMessageModel> + Send + Sync + 'static>,
+    pub max_parallel: usize,
+}
+
+impl<TMessageModel: MySbMessageDeserializer<Item = TMessageModel> + Send + Sync + 'static>
+    SingleMessageHandlerCallback<TMessageModel>
+{
+    pub fn new(
+        handler: Arc<dyn SingleMessageHandler<TMessageModel> + Send + Sync + 'static>,
+    ) -> Self {
+        Self {
+            handler,
+            max_parallel: 1,
+        }
+    }
+
+    pub fn new_parallel(
+        handler: Arc<dyn SingleMessageHandler<TMessageModel> + Send + Sync + 'static>,
+        max_parallel: usize,
+    ) -> Self {
+        Self {
+            handler,
+            max_parallel,
+        }
+    }
+
+    async fn handle_sequentially(&self, messages_reader: &mut MessagesReader<TMessageModel>) {
+        let data = messages_reader.data.clone();
+        let confirmation_id = messages_reader.confirmation_id;
+
+        while let Some(msg) = messages_reader.get_next_message() {
+            if let Err(err) = self.handler.handle(msg).await {
+                msg.nack();
+                write_handle_error(&data, confirmation_id, msg.id.get_value(), err);
+            }
+        }
+    }
+
+    async fn handle_in_parallel(&self, messages_reader: &mut MessagesReader<TMessageModel>) {
+        let messages = match messages_reader.get_all() {
+            Some(messages) => messages,
+            None => return,
+        };
+
+        let data = messages_reader.data.clone();
+        let confirmation_id = messages_reader.confirmation_id;
+
+        let mut results = futures::stream::iter(messages)
+            .map(|mut msg| async move {
+                let result = self.handler.handle(&mut msg).await;
+                (msg, result)
+            })
+            .buffer_unordered(self.max_parallel);
+
+        while let Some((msg, result)) = results.next().await {
+            match result {
+                Ok(_) => {
+                    if msg.handle_result == Some(MessageHandleResult::Nack) {
+                        messages_reader.nack(msg.id);
+                    } else {
+                        messages_reader.ack(msg.id);
+                    }
+                }
+                Err(err) => {
+                    messages_reader.nack(msg.id);
+                    write_handle_error(&data, confirmation_id, msg.id.get_value(), err);
+                }
+            }
+        }
+    }
+}
+
+pub(crate) fn write_handle_error(
+    data: &SubscriberData,
+    confirmation_id: i64,
+    message_id: i64,
+    err: MySbSubscriberHandleError,
+) {
+    let mut ctx = HashMap::new();
+    ctx.insert("topicId".to_string(), data.topic_id.as_str().to_string());
+    ctx.insert("queueId".to_string(), data.queue_id.as_str().to_string());
+    ctx.insert("confirmationId".to_string(), confirmation_id.to_string());
+    ctx.insert("messageId".to_string(), message_id.to_string());
+
+    data.logger.write_error(
+        "handle_messages".to_string(),
+        format!("Can not handle message. Err: {}", err.msg),
+        Some(ctx),
+    );
+
+    data.set_last_error(vec![message_id], &err.msg);
+}
+
+#[async_trait::async_trait]
+impl<TMessageModel: MySbMessageDeserializer<Item = TMessageModel> + Send + Sync + 'static>
+    SubscriberCallback<TMessageModel> for SingleMessageHandlerCallback<TMessageModel>
+{
+    async fn handle_messages(
+        &self,
+        messages_reader: &mut MessagesReader<TMessageModel>,
+    ) -> Result<(), MySbSubscriberHandleError> {
+        if self.max_parallel > 1 {
+            self.handle_in_parallel(messages_reader).await;
+        } else {
+            self.handle_sequentially(messages_reader).await;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::super::test_utils::*;
+    use super::*;
+
+    struct FailingHandler {
+        failing_id: i64,
+        handled: Mutex<Vec<i64>>,
+    }
+
+    #[async_trait::async_trait]
+    impl SingleMessageHandler<TestMessage> for FailingHandler {
+        async fn handle(
+            &self,
+            msg: &mut MySbDeliveredMessage<TestMessage>,
+        ) -> Result<(), MySbSubscriberHandleError> {
+            self.handled.lock().unwrap().push(msg.id.get_value());
+
+            if msg.id.get_value() == self.failing_id {
+                return Err(MySbSubscriberHandleError {
+                    msg: "Handler failed".to_string(),
+                });
+            }
+
+            Ok(())
+        }
+    }
+
+    async fn handle(
+        max_parallel: usize,
+    ) -> (Arc<TestClient>, Arc<TestLogger>, Arc<FailingHandler>) {
+        let client = TestClient::new();
+        let logger = TestLogger::new();
+        let data = create_subscriber_data(&client, &logger, Default::default());
+
+        let handler = Arc::new(FailingHandler {
+            failing_id: 2,
+            handled: Mutex::new(Vec::new()),
+        });
+
+        let callback = SingleMessageHandlerCallback::new_parallel(handler.clone(), max_parallel);
+
+        let mut reader = create_reader(&data, &[1, 2, 3], 1);
+        assert_eq!(true, callback.handle_messages(&mut reader).await.is_ok());
+        drop(reader);
+
+        (client, logger, handler)
+    }
+
+    #[tokio::test]
+    async fn test_failed_message_does_not_stop_the_batch() {
+        let (client, logger, handler) = handle(1).await;
+
+        assert_eq!(vec![1, 2, 3], *handler.handled.lock().unwrap());
+        assert_eq!(
+            vec![TestConfirmation::Some {
+                confirmation_id: 1,
+                ok_messages: vec![(1, 1), (3, 3)]
+            }],
+            client.get_confirmations()
+        );
+        assert_eq!(
+            true,
+            logger.has_message("Can not handle message. Err: Handler failed")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_parallel_handling_confirms_ok_messages() {
+        let (client, logger, handler) = handle(3).await;
+
+        let mut handled = handler.handled.lock().unwrap().clone();
+        handled.sort();
+
+        assert_eq!(vec![1, 2, 3], handled);
+        assert_eq!(
+            vec![TestConfirmation::Some {
+                confirmation_id: 1,
+                ok_messages: vec![(1, 1), (3, 3)]
+            }],
+            client.get_confirmations()
+        );
+        assert_eq!(
+            true,
+            logger.has_message("Can not handle message. Err: Handler failed")
+        );
+    }
+}
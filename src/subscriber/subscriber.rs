@@ -1,9 +1,25 @@
 use std::{
-    collections::{HashMap, VecDeque},
-    sync::Arc,
+    any::Any,
+    collections::{BTreeMap, HashMap, VecDeque},
+    future::Future,
+    panic::AssertUnwindSafe,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
 };
 
+use futures::FutureExt;
+
 use rust_extensions::{Logger, StrOrString};
+use tokio::{
+    sync::{
+        mpsc::{Receiver, Sender},
+        Notify, OwnedSemaphorePermit, Semaphore,
+    },
+    task::JoinSet,
+};
 
 use crate::{
     queue_with_intervals::QueueWithIntervals, MySbMessage, MyServiceBusSubscriberClient,
@@ -11,21 +27,427 @@ use crate::{
 };
 
 use super::{
-    MessagesReader, MySbDeliveredMessage, MySbMessageDeserializer, SubscriberCallback,
-    TopicQueueType,
+    DeadLetterPolicy, LaneDispatcher, MessagesReader, MySbDeliveredMessage,
+    MySbMessageDeserializer, MySbSubscriberHandleError, PartitionedMessageHandlerCallback,
+    PoisonMessageStrategy, SubscriberCallback, SubscriberPausePolicy, SubscriberProcessingMode,
+    SubscriberSettings, SubscriberState, TopicQueueType,
 };
 
+const MAX_LAST_ERRORS: usize = 10_000;
+
 pub struct SubscriberData {
     pub topic_id: StrOrString<'static>,
     pub queue_id: StrOrString<'static>,
     pub queue_type: TopicQueueType,
     pub logger: Arc<dyn Logger + Sync + Send + 'static>,
     pub client: Arc<dyn MyServiceBusSubscriberClient + Sync + Send + 'static>,
+    pub settings: SubscriberSettings,
+    handlers_limit: Option<Arc<Semaphore>>,
+    last_errors: std::sync::Mutex<BTreeMap<i64, String>>,
+    in_flight_readers: AtomicUsize,
+    readers_are_released: Notify,
+    is_shutting_down: std::sync::Mutex<bool>,
+}
+
+impl SubscriberData {
+    pub(crate) fn new(
+        topic_id: StrOrString<'static>,
+        queue_id: StrOrString<'static>,
+        queue_type: TopicQueueType,
+        logger: Arc<dyn Logger + Sync + Send + 'static>,
+        client: Arc<dyn MyServiceBusSubscriberClient + Sync + Send + 'static>,
+        settings: SubscriberSettings,
+    ) -> Self {
+        if settings.max_concurrent_handlers == Some(0) {
+            let mut ctx = HashMap::new();
+            ctx.insert("topicId".to_string(), topic_id.as_str().to_string());
+            ctx.insert("queueId".to_string(), queue_id.as_str().to_string());
+            logger.write_warning(
+                "Subscriber::new".to_string(),
+                "max_concurrent_handlers is 0. Handlers are not limited".to_string(),
+                Some(ctx),
+            );
+        }
+
+        let handlers_limit = settings
+            .max_concurrent_handlers
+            .filter(|max_concurrent_handlers| *max_concurrent_handlers > 0)
+            .map(|max_concurrent_handlers| Arc::new(Semaphore::new(max_concurrent_handlers)));
+
+        Self {
+            topic_id,
+            queue_id,
+            queue_type,
+            client,
+            logger,
+            settings,
+            handlers_limit,
+            last_errors: std::sync::Mutex::new(BTreeMap::new()),
+            in_flight_readers: AtomicUsize::new(0),
+            readers_are_released: Notify::new(),
+            is_shutting_down: std::sync::Mutex::new(false),
+        }
+    }
+
+    fn is_shutting_down(&self) -> bool {
+        *self.is_shutting_down.lock().unwrap()
+    }
+
+    fn start_shutdown(&self) {
+        *self.is_shutting_down.lock().unwrap() = true;
+    }
+
+    // Delivery is counted as in flight until its reader is created so shutdown can not miss it
+    fn try_register_delivery(&self) -> bool {
+        let is_shutting_down = self.is_shutting_down.lock().unwrap();
+
+        if *is_shutting_down {
+            return false;
+        }
+
+        self.reader_is_created();
+        true
+    }
+
+    pub(crate) fn reader_is_created(&self) {
+        self.in_flight_readers.fetch_add(1, Ordering::SeqCst);
+    }
+
+    pub(crate) fn reader_is_released(&self) {
+        if self.in_flight_readers.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.readers_are_released.notify_waiters();
+        }
+    }
+
+    async fn wait_until_readers_are_released(&self) {
+        loop {
+            let readers_are_released = self.readers_are_released.notified();
+            tokio::pin!(readers_are_released);
+            readers_are_released.as_mut().enable();
+
+            if self.in_flight_readers.load(Ordering::SeqCst) == 0 {
+                return;
+            }
+
+            readers_are_released.await;
+        }
+    }
+
+    async fn acquire_handler_permits(&self) -> Vec<OwnedSemaphorePermit> {
+        let mut result = Vec::with_capacity(2);
+
+        let limits = [
+            self.handlers_limit.as_ref(),
+            self.settings.shared_handlers_limit.as_ref(),
+        ];
+
+        for limit in limits.into_iter().flatten() {
+            if let Ok(permit) = limit.clone().acquire_owned().await {
+                result.push(permit);
+            }
+        }
+
+        result
+    }
+
+    pub(crate) fn set_last_error(&self, ids: Vec<i64>, err: &str) {
+        if self.settings.dead_letter.is_none() {
+            return;
+        }
+
+        let mut last_errors = self.last_errors.lock().unwrap();
+        for id in ids {
+            last_errors.insert(id, err.to_string());
+        }
+
+        while last_errors.len() > MAX_LAST_ERRORS {
+            last_errors.pop_first();
+        }
+    }
+
+    pub(crate) fn messages_are_delivered(&self, ids: &QueueWithIntervals) {
+        if self.settings.dead_letter.is_none() {
+            return;
+        }
+
+        let mut last_errors = self.last_errors.lock().unwrap();
+        for id in ids {
+            last_errors.remove(&id);
+        }
+    }
+
+    fn take_exceeded(&self, messages: Vec<MySbMessage>) -> (Vec<MySbMessage>, Vec<MySbMessage>) {
+        match self.settings.dead_letter.as_ref() {
+            Some(dead_letter) => messages
+                .into_iter()
+                .partition(|msg| !dead_letter.is_exceeded(msg)),
+            None => (messages, Vec::new()),
+        }
+    }
+
+    async fn dead_letter_exceeded(&self, exceeded: Vec<MySbMessage>) -> Vec<(i64, bool)> {
+        let dead_letter = match self.settings.dead_letter.as_ref() {
+            Some(dead_letter) => dead_letter,
+            None => return Vec::new(),
+        };
+
+        if exceeded.is_empty() {
+            return Vec::new();
+        }
+
+        let to_publish: Vec<(&MySbMessage, Option<String>)> = {
+            let mut last_errors = self.last_errors.lock().unwrap();
+            exceeded
+                .iter()
+                .map(|msg| (msg, last_errors.remove(&msg.id.get_value())))
+                .collect()
+        };
+
+        let published = self
+            .publish_to_dead_letter(
+                dead_letter,
+                &to_publish,
+                format!(
+                    "Messages exceeded {} delivery attempts and are moved to dead letter topic",
+                    dead_letter.max_attempts
+                ),
+            )
+            .await;
+
+        exceeded
+            .iter()
+            .map(|msg| (msg.id.get_value(), published))
+            .collect()
+    }
+
+    async fn publish_to_dead_letter(
+        &self,
+        dead_letter: &DeadLetterPolicy,
+        messages: &[(&MySbMessage, Option<String>)],
+        reason: String,
+    ) -> bool {
+        let result = dead_letter
+            .publish(self.topic_id.as_str(), self.queue_id.as_str(), messages)
+            .await;
+
+        let mut ctx = HashMap::new();
+        ctx.insert("topicId".to_string(), self.topic_id.as_str().to_string());
+        ctx.insert("queueId".to_string(), self.queue_id.as_str().to_string());
+        ctx.insert(
+            "deadLetterTopicId".to_string(),
+            dead_letter.topic_id.to_string(),
+        );
+        ctx.insert(
+            "messages".to_string(),
+            format!(
+                "{:?}",
+                messages
+                    .iter()
+                    .map(|(msg, _)| msg.id.get_value())
+                    .collect::<Vec<_>>()
+            ),
+        );
+
+        match result {
+            Ok(_) => {
+                self.logger
+                    .write_warning("new_events".to_string(), reason, Some(ctx));
+                true
+            }
+            Err(err) => {
+                self.logger.write_error(
+                    "new_events".to_string(),
+                    format!(
+                        "Can not publish messages to dead letter topic. Err: {:?}",
+                        err
+                    ),
+                    Some(ctx),
+                );
+                false
+            }
+        }
+    }
+
+    async fn handle_poison_messages(
+        &self,
+        confirmation_id: i64,
+        poison_messages: Vec<(MySbMessage, String)>,
+    ) -> Vec<(i64, bool)> {
+        if poison_messages.is_empty() {
+            return Vec::new();
+        }
+
+        let mut ctx = HashMap::new();
+        ctx.insert("topicId".to_string(), self.topic_id.as_str().to_string());
+        ctx.insert("queueId".to_string(), self.queue_id.as_str().to_string());
+        ctx.insert(
+            "messages".to_string(),
+            format!(
+                "{:?}",
+                poison_messages
+                    .iter()
+                    .map(|(msg, _)| msg.id.get_value())
+                    .collect::<Vec<_>>()
+            ),
+        );
+        ctx.insert("confirmationId".to_string(), confirmation_id.to_string());
+        ctx.insert(
+            "strategy".to_string(),
+            format!("{:?}", self.settings.poison_message_strategy),
+        );
+
+        self.logger.write_fatal_error(
+            "new_events".to_string(),
+            format!(
+                "Can not deserialize messages. Err: {}",
+                poison_messages[0].1
+            ),
+            Some(ctx),
+        );
+
+        match &self.settings.poison_message_strategy {
+            PoisonMessageStrategy::Reject { max_attempts } => {
+                let (exceeded, rejected): (Vec<_>, Vec<_>) = poison_messages
+                    .iter()
+                    .partition(|(msg, _)| msg.attempt_no >= *max_attempts);
+
+                let mut result: Vec<(i64, bool)> = rejected
+                    .iter()
+                    .map(|(msg, _)| (msg.id.get_value(), false))
+                    .collect();
+
+                if exceeded.is_empty() {
+                    return result;
+                }
+
+                let reason = format!(
+                    "Messages can not be deserialized after {} delivery attempts",
+                    max_attempts
+                );
+
+                let delivered = match self.settings.dead_letter.as_ref() {
+                    Some(dead_letter) => {
+                        let to_publish: Vec<(&MySbMessage, Option<String>)> = exceeded
+                            .iter()
+                            .map(|(msg, err)| (msg, Some(err.to_string())))
+                            .collect();
+
+                        self.publish_to_dead_letter(
+                            dead_letter,
+                            &to_publish,
+                            format!("{} and are moved to dead letter topic", reason),
+                        )
+                        .await
+                    }
+                    None => {
+                        let mut ctx = HashMap::new();
+                        ctx.insert("topicId".to_string(), self.topic_id.as_str().to_string());
+                        ctx.insert("queueId".to_string(), self.queue_id.as_str().to_string());
+                        ctx.insert(
+                            "messages".to_string(),
+                            format!(
+                                "{:?}",
+                                exceeded
+                                    .iter()
+                                    .map(|(msg, _)| msg.id.get_value())
+                                    .collect::<Vec<_>>()
+                            ),
+                        );
+                        self.logger.write_fatal_error(
+                            "new_events".to_string(),
+                            format!("{} and are dropped", reason),
+                            Some(ctx),
+                        );
+
+                        true
+                    }
+                };
+
+                result.extend(
+                    exceeded
+                        .iter()
+                        .map(|(msg, _)| (msg.id.get_value(), delivered)),
+                );
+
+                result
+            }
+            PoisonMessageStrategy::Quarantine(store) => {
+                let mut result = Vec::with_capacity(poison_messages.len());
+
+                for (msg, err) in &poison_messages {
+                    let stored = store
+                        .store(self.topic_id.as_str(), self.queue_id.as_str(), msg, err)
+                        .await;
+
+                    if let Err(store_err) = &stored {
+                        let mut ctx = HashMap::new();
+                        ctx.insert("topicId".to_string(), self.topic_id.as_str().to_string());
+                        ctx.insert("queueId".to_string(), self.queue_id.as_str().to_string());
+                        ctx.insert("messageId".to_string(), msg.id.get_value().to_string());
+                        self.logger.write_error(
+                            "new_events".to_string(),
+                            format!("Can not quarantine message. Err: {}", store_err),
+                            Some(ctx),
+                        );
+                    }
+
+                    result.push((msg.id.get_value(), stored.is_ok()));
+                }
+
+                result
+            }
+            PoisonMessageStrategy::DeadLetter(dead_letter) => {
+                let to_publish: Vec<(&MySbMessage, Option<String>)> = poison_messages
+                    .iter()
+                    .map(|(msg, err)| (msg, Some(err.to_string())))
+                    .collect();
+
+                let published = self
+                    .publish_to_dead_letter(
+                        dead_letter,
+                        &to_publish,
+                        "Messages can not be deserialized and are moved to dead letter topic"
+                            .to_string(),
+                    )
+                    .await;
+
+                poison_messages
+                    .iter()
+                    .map(|(msg, _)| (msg.id.get_value(), published))
+                    .collect()
+            }
+        }
+    }
+}
+
+struct PausedDelivery {
+    messages: Vec<MySbMessage>,
+    confirmation_id: i64,
+    connection_id: i32,
+}
+
+struct PausedDeliveries {
+    is_paused: bool,
+    is_resuming: bool,
+    deliveries: VecDeque<PausedDelivery>,
+}
+
+struct Delivery<TMessageModel: MySbMessageDeserializer<Item = TMessageModel>> {
+    reader: MessagesReader<TMessageModel>,
+    exceeded: Vec<MySbMessage>,
+    poison_messages: Vec<(MySbMessage, String)>,
+    has_messages_to_handle: bool,
+    permits: Vec<OwnedSemaphorePermit>,
 }
 
 pub struct Subscriber<TMessageModel: MySbMessageDeserializer<Item = TMessageModel>> {
     data: Arc<SubscriberData>,
+    paused_deliveries: std::sync::Mutex<PausedDeliveries>,
+    resume_lock: tokio::sync::Mutex<()>,
+    sequential_queue: std::sync::Mutex<Option<Sender<Delivery<TMessageModel>>>>,
+    handler_tasks: std::sync::Mutex<JoinSet<()>>,
     pub callback: Arc<dyn SubscriberCallback<TMessageModel> + Sync + Send + 'static>,
+    lane_dispatcher: Option<Arc<dyn LaneDispatcher<TMessageModel> + Sync + Send + 'static>>,
 }
 
 impl<TMessageModel: MySbMessageDeserializer<Item = TMessageModel> + Send + Sync + 'static>
@@ -39,100 +461,354 @@ impl<TMessageModel: MySbMessageDeserializer<Item = TMessageModel> + Send + Sync
         logger: Arc<dyn Logger + Sync + Send + 'static>,
         client: Arc<dyn MyServiceBusSubscriberClient + Sync + Send + 'static>,
     ) -> Self {
-        let data = SubscriberData {
+        Self::new_with_settings(
             topic_id,
             queue_id,
             queue_type,
-            client,
+            callback,
             logger,
-        };
+            client,
+            Default::default(),
+        )
+    }
+
+    pub fn new_with_settings(
+        topic_id: StrOrString<'static>,
+        queue_id: StrOrString<'static>,
+        queue_type: TopicQueueType,
+        callback: Arc<dyn SubscriberCallback<TMessageModel> + Sync + Send + 'static>,
+        logger: Arc<dyn Logger + Sync + Send + 'static>,
+        client: Arc<dyn MyServiceBusSubscriberClient + Sync + Send + 'static>,
+        settings: SubscriberSettings,
+    ) -> Self {
+        let data = SubscriberData::new(topic_id, queue_id, queue_type, logger, client, settings);
+
         Self {
             callback,
+            paused_deliveries: std::sync::Mutex::new(PausedDeliveries {
+                is_paused: false,
+                is_resuming: false,
+                deliveries: VecDeque::new(),
+            }),
+            resume_lock: tokio::sync::Mutex::new(()),
+            sequential_queue: std::sync::Mutex::new(None),
+            handler_tasks: std::sync::Mutex::new(JoinSet::new()),
+            lane_dispatcher: None,
             data: Arc::new(data),
         }
     }
-}
 
-#[async_trait::async_trait]
-impl<TMessageModel: MySbMessageDeserializer<Item = TMessageModel> + Send + Sync + 'static>
-    MyServiceBusSubscriberClientCallback for Subscriber<TMessageModel>
-{
-    fn get_topic_id(&self) -> &str {
-        self.data.topic_id.as_str()
+    pub fn new_partitioned_with_settings(
+        topic_id: StrOrString<'static>,
+        queue_id: StrOrString<'static>,
+        queue_type: TopicQueueType,
+        callback: Arc<PartitionedMessageHandlerCallback<TMessageModel>>,
+        logger: Arc<dyn Logger + Sync + Send + 'static>,
+        client: Arc<dyn MyServiceBusSubscriberClient + Sync + Send + 'static>,
+        settings: SubscriberSettings,
+    ) -> Self {
+        let mut result = Self::new_with_settings(
+            topic_id,
+            queue_id,
+            queue_type,
+            callback.clone(),
+            logger,
+            client,
+            settings,
+        );
+
+        result.lane_dispatcher = Some(callback);
+        result
     }
 
-    fn get_queue_id(&self) -> &str {
-        self.data.queue_id.as_str()
+    pub fn get_in_flight_deliveries(&self) -> usize {
+        self.data.in_flight_readers.load(Ordering::SeqCst)
     }
-    fn get_queue_type(&self) -> TopicQueueType {
-        self.data.queue_type
+
+    pub fn is_shutting_down(&self) -> bool {
+        self.data.is_shutting_down()
     }
 
-    async fn new_events(
+    pub fn get_state(&self) -> SubscriberState {
+        if self.is_shutting_down() {
+            return SubscriberState::ShuttingDown;
+        }
+
+        if self.paused_deliveries.lock().unwrap().is_paused {
+            return SubscriberState::Paused;
+        }
+
+        SubscriberState::Active
+    }
+
+    pub fn get_paused_deliveries_amount(&self) -> usize {
+        self.paused_deliveries.lock().unwrap().deliveries.len()
+    }
+
+    pub fn pause(&self) {
+        let mut paused_deliveries = self.paused_deliveries.lock().unwrap();
+        paused_deliveries.is_paused = true;
+        paused_deliveries.is_resuming = false;
+    }
+
+    pub async fn resume(&self) {
+        let _resume_guard = self.resume_lock.lock().await;
+
+        self.paused_deliveries.lock().unwrap().is_resuming = true;
+
+        loop {
+            let delivery = {
+                let mut paused_deliveries = self.paused_deliveries.lock().unwrap();
+
+                if !paused_deliveries.is_resuming {
+                    return;
+                }
+
+                match paused_deliveries.deliveries.pop_front() {
+                    Some(delivery) => delivery,
+                    None => {
+                        paused_deliveries.is_paused = false;
+                        paused_deliveries.is_resuming = false;
+                        return;
+                    }
+                }
+            };
+
+            if !self.data.try_register_delivery() {
+                self.reject_delivery(
+                    delivery.confirmation_id,
+                    delivery.connection_id,
+                    "Subscriber is shutting down. Delivery is rejected",
+                );
+                continue;
+            }
+
+            self.handle_delivery(
+                delivery.messages,
+                delivery.confirmation_id,
+                delivery.connection_id,
+            )
+            .await;
+        }
+    }
+
+    async fn handle_delivery(
         &self,
         messages_to_deliver: Vec<MySbMessage>,
         confirmation_id: i64,
         connection_id: i32,
     ) {
-        let mut messages = VecDeque::with_capacity(messages_to_deliver.len());
+        let (messages_to_deliver, exceeded) = self.data.take_exceeded(messages_to_deliver);
 
-        let mut can_not_serialize_messages = QueueWithIntervals::new();
+        let mut messages = VecDeque::with_capacity(messages_to_deliver.len());
 
-        let mut deserialize_error = None;
+        let mut poison_messages = Vec::new();
 
         for msg in messages_to_deliver {
             let content_result = TMessageModel::deserialize(&msg.content, &msg.headers);
 
             match content_result {
                 Ok(contract) => {
-                    #[cfg(feature = "with-telemetry")]
-                    let mut msg = MySbDeliveredMessage {
-                        id: msg.id,
-                        attempt_no: msg.attempt_no,
-                        headers: msg.headers,
-                        content: Some(contract),
-                        raw: msg.content,
-                        my_telemetry_ctx: None,
-                        event_tracker: None,
-                    };
+                    #[allow(unused_mut)]
+                    let mut msg = MySbDeliveredMessage::new(
+                        msg.id,
+                        msg.attempt_no,
+                        msg.headers,
+                        msg.content,
+                        contract,
+                    );
 
                     #[cfg(feature = "with-telemetry")]
                     msg.init_telemetry_context(self.get_topic_id(), self.get_queue_id());
 
-                    #[cfg(not(feature = "with-telemetry"))]
-                    let msg = MySbDeliveredMessage {
-                        id: msg.id,
-                        attempt_no: msg.attempt_no,
-                        headers: msg.headers,
-                        content: Some(contract),
-                        raw: msg.content,
-                    };
-
                     messages.push_back(msg);
                 }
                 Err(err) => {
-                    if deserialize_error.is_none() {
-                        deserialize_error = Some(format!(
-                            "Can not deserialize one of the messages. Err:{:?}",
-                            err
-                        ));
-                    }
-                    can_not_serialize_messages.enqueue(msg.id.get_value());
+                    poison_messages.push((msg, format!("{:?}", err)));
                 }
             }
         }
 
-        if messages.len() == 0 {
-            self.data.client.confirm_delivery(
-                self.data.topic_id.as_str(),
-                self.data.queue_id.as_str(),
-                confirmation_id,
-                connection_id,
-                true,
+        let has_messages_to_handle = !messages.is_empty();
+
+        let reader =
+            MessagesReader::new(self.data.clone(), messages, confirmation_id, connection_id);
+
+        self.data.reader_is_released();
+
+        let mut delivery = Delivery {
+            reader,
+            exceeded,
+            poison_messages,
+            has_messages_to_handle,
+            permits: Vec::new(),
+        };
+
+        let callback = self.callback.clone();
+
+        if has_messages_to_handle {
+            delivery.permits = self.data.acquire_handler_permits().await;
+        }
+
+        if self.data.settings.processing_mode == SubscriberProcessingMode::Sequential {
+            self.enqueue_sequential(delivery).await;
+            return;
+        }
+
+        if !has_messages_to_handle {
+            self.spawn_handler(process_delivery(callback, delivery));
+            return;
+        }
+
+        if let Some(lane_dispatcher) = self.lane_dispatcher.as_ref() {
+            lane_dispatcher.dispatch_messages(&mut delivery.reader);
+        }
+
+        self.spawn_handler(process_delivery(callback, delivery));
+    }
+
+    fn spawn_handler(&self, handler: impl Future<Output = ()> + Send + 'static) {
+        let mut handler_tasks = self.handler_tasks.lock().unwrap();
+        while handler_tasks.try_join_next().is_some() {}
+        handler_tasks.spawn(handler);
+    }
+
+    fn hold_if_paused(
+        &self,
+        messages: Vec<MySbMessage>,
+        confirmation_id: i64,
+        connection_id: i32,
+    ) -> Option<Vec<MySbMessage>> {
+        {
+            let mut paused_deliveries = self.paused_deliveries.lock().unwrap();
+
+            if !paused_deliveries.is_paused {
+                return Some(messages);
+            }
+
+            if self.data.is_shutting_down() {
+                drop(paused_deliveries);
+                self.reject_delivery(
+                    confirmation_id,
+                    connection_id,
+                    "Subscriber is shutting down. Delivery is rejected",
+                );
+                return None;
+            }
+
+            if let SubscriberPausePolicy::Buffer { max_deliveries } =
+                self.data.settings.pause_policy
+            {
+                if paused_deliveries.deliveries.len() < max_deliveries {
+                    paused_deliveries.deliveries.push_back(PausedDelivery {
+                        messages,
+                        confirmation_id,
+                        connection_id,
+                    });
+                    return None;
+                }
+            }
+        }
+
+        self.reject_delivery(
+            confirmation_id,
+            connection_id,
+            "Subscriber is paused. Delivery is rejected",
+        );
+
+        None
+    }
+
+    fn reject_delivery(&self, confirmation_id: i64, connection_id: i32, reason: &str) {
+        self.data.client.confirm_delivery(
+            self.data.topic_id.as_str(),
+            self.data.queue_id.as_str(),
+            confirmation_id,
+            connection_id,
+            false,
+        );
+
+        let mut ctx = HashMap::new();
+        ctx.insert(
+            "topicId".to_string(),
+            self.data.topic_id.as_str().to_string(),
+        );
+        ctx.insert(
+            "queueId".to_string(),
+            self.data.queue_id.as_str().to_string(),
+        );
+        ctx.insert("confirmationId".to_string(), confirmation_id.to_string());
+        self.data
+            .logger
+            .write_warning("new_events".to_string(), reason.to_string(), Some(ctx));
+    }
+
+    pub async fn shutdown(&self, timeout: Duration) -> usize {
+        self.data.start_shutdown();
+
+        let paused_deliveries =
+            std::mem::take(&mut self.paused_deliveries.lock().unwrap().deliveries);
+
+        for delivery in paused_deliveries {
+            self.reject_delivery(
+                delivery.confirmation_id,
+                delivery.connection_id,
+                "Subscriber is shutting down. Delivery is rejected",
             );
+        }
 
+        let _ = tokio::time::timeout(timeout, self.data.wait_until_readers_are_released()).await;
+
+        let abandoned = self.get_in_flight_deliveries();
+
+        if abandoned > 0 {
             let mut ctx = HashMap::new();
+            ctx.insert(
+                "topicId".to_string(),
+                self.data.topic_id.as_str().to_string(),
+            );
+            ctx.insert(
+                "queueId".to_string(),
+                self.data.queue_id.as_str().to_string(),
+            );
+            self.data.logger.write_error(
+                "shutdown".to_string(),
+                format!(
+                    "Subscriber is stopped. {} deliveries are still being handled and are aborted",
+                    abandoned
+                ),
+                Some(ctx),
+            );
+        }
+
+        self.handler_tasks.lock().unwrap().abort_all();
+        *self.sequential_queue.lock().unwrap() = None;
+
+        if let Some(lane_dispatcher) = self.lane_dispatcher.as_ref() {
+            lane_dispatcher.abort_lanes();
+        }
+
+        abandoned
+    }
+
+    async fn enqueue_sequential(&self, delivery: Delivery<TMessageModel>) {
+        let sender = {
+            let mut sequential_queue = self.sequential_queue.lock().unwrap();
 
+            if sequential_queue.is_none() {
+                let (sender, receiver) =
+                    tokio::sync::mpsc::channel(self.data.settings.max_pending_deliveries.max(1));
+
+                self.spawn_handler(sequential_worker(self.callback.clone(), receiver));
+
+                *sequential_queue = Some(sender);
+            }
+
+            sequential_queue.as_ref().unwrap().clone()
+        };
+
+        if let Err(err) = sender.send(delivery).await {
+            let mut ctx = HashMap::new();
             ctx.insert(
                 "topicId".to_string(),
                 self.data.topic_id.as_str().to_string(),
@@ -142,48 +818,985 @@ impl<TMessageModel: MySbMessageDeserializer<Item = TMessageModel> + Send + Sync
                 self.data.queue_id.as_str().to_string(),
             );
             ctx.insert(
-                "messages".to_string(),
-                format!("{:?}", can_not_serialize_messages),
+                "confirmationId".to_string(),
+                err.0.reader.confirmation_id.to_string(),
             );
-            ctx.insert("confirmationId".to_string(), confirmation_id.to_string());
-
             self.data.logger.write_fatal_error(
                 "new_events".to_string(),
-                format!("Can not serialize messages"),
+                "Sequential handler is stopped".to_string(),
                 Some(ctx),
             );
+
+            *self.sequential_queue.lock().unwrap() = None;
+        }
+    }
+}
+
+async fn sequential_worker<
+    TMessageModel: MySbMessageDeserializer<Item = TMessageModel> + Send + Sync + 'static,
+>(
+    callback: Arc<dyn SubscriberCallback<TMessageModel> + Sync + Send + 'static>,
+    mut receiver: Receiver<Delivery<TMessageModel>>,
+) {
+    while let Some(delivery) = receiver.recv().await {
+        process_delivery(callback.clone(), delivery).await;
+    }
+}
+
+async fn process_delivery<
+    TMessageModel: MySbMessageDeserializer<Item = TMessageModel> + Send + Sync + 'static,
+>(
+    callback: Arc<dyn SubscriberCallback<TMessageModel> + Sync + Send + 'static>,
+    delivery: Delivery<TMessageModel>,
+) {
+    let mut reader = delivery.reader;
+    let data = reader.data.clone();
+
+    let dead_lettered = data.dead_letter_exceeded(delivery.exceeded).await;
+
+    let poison_messages = data
+        .handle_poison_messages(reader.confirmation_id, delivery.poison_messages)
+        .await;
+
+    for (id, delivered) in dead_lettered.into_iter().chain(poison_messages) {
+        reader.add_handled_message(id, delivered);
+    }
+
+    if delivery.has_messages_to_handle {
+        handle_messages(callback, reader).await;
+    }
+
+    drop(delivery.permits);
+}
+
+async fn run_handler<
+    TMessageModel: MySbMessageDeserializer<Item = TMessageModel> + Send + Sync + 'static,
+>(
+    callback: &Arc<dyn SubscriberCallback<TMessageModel> + Sync + Send + 'static>,
+    reader: &mut MessagesReader<TMessageModel>,
+) -> Result<(), MySbSubscriberHandleError> {
+    let result = AssertUnwindSafe(callback.handle_messages(reader))
+        .catch_unwind()
+        .await;
+
+    match result {
+        Ok(result) => result,
+        Err(panic) => {
+            let current_message_id = reader.nack_current_message();
+
+            Err(MySbSubscriberHandleError {
+                msg: format!(
+                    "Handler panicked. Current message: {:?}. Panic: {}",
+                    current_message_id.map(|id| id.get_value()),
+                    get_panic_message(panic.as_ref())
+                ),
+            })
+        }
+    }
+}
+
+pub(crate) fn get_panic_message(panic: &(dyn Any + Send)) -> String {
+    if let Some(msg) = panic.downcast_ref::<&str>() {
+        return msg.to_string();
+    }
+
+    if let Some(msg) = panic.downcast_ref::<String>() {
+        return msg.to_string();
+    }
+
+    "Unknown panic payload".to_string()
+}
+
+async fn handle_messages<
+    TMessageModel: MySbMessageDeserializer<Item = TMessageModel> + Send + Sync + 'static,
+>(
+    callback: Arc<dyn SubscriberCallback<TMessageModel> + Sync + Send + 'static>,
+    mut reader: MessagesReader<TMessageModel>,
+) {
+    let result = match reader.data.settings.handler_timeout {
+        Some(handler_timeout) => {
+            match tokio::time::timeout(handler_timeout, run_handler(&callback, &mut reader)).await {
+                Ok(result) => result,
+                Err(_) => Err(MySbSubscriberHandleError {
+                    msg: format!("Handler timeout {:?} is expired", handler_timeout),
+                }),
+            }
+        }
+        None => run_handler(&callback, &mut reader).await,
+    };
+
+    if let Err(err) = result {
+        let not_delivered_ids = reader.get_not_delivered_ids();
+
+        let mut ctx = HashMap::new();
+
+        ctx.insert(
+            "topicId".to_string(),
+            reader.data.topic_id.as_str().to_string(),
+        );
+        ctx.insert(
+            "queueId".to_string(),
+            reader.data.queue_id.as_str().to_string(),
+        );
+        ctx.insert(
+            "confirmationId".to_string(),
+            reader.confirmation_id.to_string(),
+        );
+        ctx.insert("messages".to_string(), format!("{:?}", not_delivered_ids));
+        reader.data.logger.write_fatal_error(
+            "new_events".to_string(),
+            format!("Can not handle messages. Err: {}", err.msg),
+            Some(ctx),
+        );
+
+        reader.data.set_last_error(not_delivered_ids, &err.msg);
+    }
+}
+
+#[async_trait::async_trait]
+impl<TMessageModel: MySbMessageDeserializer<Item = TMessageModel> + Send + Sync + 'static>
+    MyServiceBusSubscriberClientCallback for Subscriber<TMessageModel>
+{
+    fn get_topic_id(&self) -> &str {
+        self.data.topic_id.as_str()
+    }
+
+    fn get_queue_id(&self) -> &str {
+        self.data.queue_id.as_str()
+    }
+    fn get_queue_type(&self) -> TopicQueueType {
+        self.data.queue_type
+    }
+
+    async fn new_events(
+        &self,
+        messages_to_deliver: Vec<MySbMessage>,
+        confirmation_id: i64,
+        connection_id: i32,
+    ) {
+        if !self.data.try_register_delivery() {
+            self.reject_delivery(
+                confirmation_id,
+                connection_id,
+                "Subscriber is shutting down. Delivery is rejected",
+            );
             return;
         }
 
-        let reader =
-            MessagesReader::new(self.data.clone(), messages, confirmation_id, connection_id);
+        let messages_to_deliver =
+            match self.hold_if_paused(messages_to_deliver, confirmation_id, connection_id) {
+                Some(messages_to_deliver) => messages_to_deliver,
+                None => {
+                    self.data.reader_is_released();
+                    return;
+                }
+            };
 
-        let callback = self.callback.clone();
+        self.handle_delivery(messages_to_deliver, confirmation_id, connection_id)
+            .await;
+    }
+}
 
-        tokio::spawn(async move {
-            let mut reader = reader;
+#[cfg(test)]
+mod tests {
+    use super::super::test_utils::*;
+    use super::*;
 
-            if let Err(err) = callback.handle_messages(&mut reader).await {
-                let mut ctx = HashMap::new();
+    #[tokio::test]
+    async fn test_zero_max_concurrent_handlers_does_not_block() {
+        let client = TestClient::new();
+        let logger = TestLogger::new();
+        let callback = TestCallback::new();
 
-                ctx.insert(
-                    "topicId".to_string(),
-                    reader.data.topic_id.as_str().to_string(),
-                );
-                ctx.insert(
-                    "queueId".to_string(),
-                    reader.data.queue_id.as_str().to_string(),
-                );
-                ctx.insert(
-                    "confirmationId".to_string(),
-                    reader.confirmation_id.to_string(),
-                );
-                reader.data.logger.write_fatal_error(
-                    "new_events".to_string(),
-                    format!("Can not handle messages. Err: {}", err.msg),
-                    Some(ctx),
-                );
+        let subscriber = create_subscriber(
+            &client,
+            &logger,
+            callback.clone(),
+            SubscriberSettings {
+                max_concurrent_handlers: Some(0),
+                ..Default::default()
+            },
+        );
+
+        assert_eq!(true, logger.has_message("max_concurrent_handlers is 0"));
+
+        tokio::time::timeout(
+            Duration::from_secs(1),
+            subscriber.new_events(create_messages(&[1, 2]), 1, 0),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            vec![TestConfirmation::All {
+                confirmation_id: 1,
+                delivered: true
+            }],
+            client.wait_for_confirmations(1).await
+        );
+        assert_eq!(vec![vec![1, 2]], callback.get_handled());
+    }
+
+    #[tokio::test]
+    async fn test_exceeded_message_is_moved_to_dead_letter_and_confirmed() {
+        let client = TestClient::new();
+        let callback = TestCallback::new();
+
+        let subscriber = create_subscriber(
+            &client,
+            &TestLogger::new(),
+            callback.clone(),
+            SubscriberSettings {
+                dead_letter: Some(DeadLetterPolicy::new(
+                    3,
+                    "dead-letter".to_string(),
+                    client.clone(),
+                )),
+                ..Default::default()
+            },
+        );
+
+        subscriber.data.set_last_error(vec![1], "Handler failed");
+
+        subscriber
+            .new_events(vec![create_message(1, 3, 1), create_message(2, 0, 2)], 1, 0)
+            .await;
+
+        assert_eq!(
+            vec![TestConfirmation::All {
+                confirmation_id: 1,
+                delivered: true
+            }],
+            client.wait_for_confirmations(1).await
+        );
+        assert_eq!(vec![vec![2]], callback.get_handled());
+
+        let published = client.get_published();
+        assert_eq!(1, published.len());
+        assert_eq!("dead-letter", published[0].0);
+        assert_eq!(vec![1], published[0].1.content);
+
+        let headers = published[0].1.headers.as_ref().unwrap();
+        assert_eq!("3", headers[super::super::DEAD_LETTER_ATTEMPT_HEADER]);
+        assert_eq!(
+            "Handler failed",
+            headers[super::super::DEAD_LETTER_LAST_ERROR_HEADER]
+        );
+
+        assert_eq!(0, subscriber.data.last_errors.lock().unwrap().len());
+    }
+
+    #[tokio::test]
+    async fn test_dead_letter_publish_does_not_block_new_events() {
+        let client = TestClient::new();
+        let dead_letter_client =
+            crate::test_utils::TestPublisherClient::new_with_delay(Duration::from_millis(500));
+
+        let subscriber = create_subscriber(
+            &client,
+            &TestLogger::new(),
+            TestCallback::new(),
+            SubscriberSettings {
+                dead_letter: Some(DeadLetterPolicy::new(
+                    3,
+                    "dead-letter".to_string(),
+                    dead_letter_client.clone(),
+                )),
+                ..Default::default()
+            },
+        );
+
+        tokio::time::timeout(
+            Duration::from_millis(100),
+            subscriber.new_events(vec![create_message(1, 5, 1)], 1, 0),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(0, client.get_confirmations().len());
+
+        assert_eq!(
+            vec![TestConfirmation::All {
+                confirmation_id: 1,
+                delivered: true
+            }],
+            client.wait_for_confirmations(1).await
+        );
+        assert_eq!(
+            vec![vec![1]],
+            dead_letter_client.get_published_contents_raw()
+        );
+    }
+
+    #[test]
+    fn test_last_errors_are_bounded() {
+        let client = TestClient::new();
+        let data = create_subscriber_data(
+            &client,
+            &TestLogger::new(),
+            SubscriberSettings {
+                dead_letter: Some(DeadLetterPolicy::new(
+                    3,
+                    "dead-letter".to_string(),
+                    client.clone(),
+                )),
+                ..Default::default()
+            },
+        );
+
+        data.set_last_error((0..MAX_LAST_ERRORS as i64 + 10).collect(), "err");
+
+        let last_errors = data.last_errors.lock().unwrap();
+        assert_eq!(MAX_LAST_ERRORS, last_errors.len());
+        assert_eq!(Some(&10), last_errors.keys().next());
+    }
+
+    async fn deliver(
+        client: &Arc<TestClient>,
+        logger: &Arc<TestLogger>,
+        settings: SubscriberSettings,
+        messages: Vec<MySbMessage>,
+    ) -> (TestConfirmation, Vec<Vec<i64>>) {
+        let callback = TestCallback::new();
+        let subscriber = create_subscriber(client, logger, callback.clone(), settings);
+
+        subscriber.new_events(messages, 1, 0).await;
+
+        let confirmation = client.wait_for_confirmations(1).await.remove(0);
+        (confirmation, callback.get_handled())
+    }
+
+    fn create_dead_letter(client: &Arc<TestClient>, max_attempts: i32) -> DeadLetterPolicy {
+        DeadLetterPolicy::new(max_attempts, "dead-letter".to_string(), client.clone())
+    }
+
+    #[tokio::test]
+    async fn test_reject_strategy() {
+        let settings = SubscriberSettings::default();
+
+        let client = TestClient::new();
+        let (confirmation, handled) = deliver(
+            &client,
+            &TestLogger::new(),
+            settings.clone(),
+            vec![create_message(1, 0, 1), create_message(2, 0, BAD_CONTENT)],
+        )
+        .await;
+
+        assert_eq!(
+            TestConfirmation::Some {
+                confirmation_id: 1,
+                ok_messages: vec![(1, 1)]
+            },
+            confirmation
+        );
+        assert_eq!(vec![vec![1]], handled);
+
+        let client = TestClient::new();
+        let (confirmation, handled) = deliver(
+            &client,
+            &TestLogger::new(),
+            settings,
+            vec![
+                create_message(1, 0, BAD_CONTENT),
+                create_message(2, 0, BAD_CONTENT),
+            ],
+        )
+        .await;
+
+        assert_eq!(
+            TestConfirmation::All {
+                confirmation_id: 1,
+                delivered: false
+            },
+            confirmation
+        );
+        assert_eq!(0, handled.len());
+    }
+
+    #[tokio::test]
+    async fn test_reject_strategy_drops_message_after_max_attempts() {
+        let client = TestClient::new();
+        let logger = TestLogger::new();
+
+        let (confirmation, _) = deliver(
+            &client,
+            &logger,
+            SubscriberSettings::default(),
+            vec![
+                create_message(
+                    1,
+                    super::super::DEFAULT_POISON_MESSAGE_MAX_ATTEMPTS,
+                    BAD_CONTENT,
+                ),
+                create_message(2, 0, BAD_CONTENT),
+            ],
+        )
+        .await;
+
+        assert_eq!(
+            TestConfirmation::Some {
+                confirmation_id: 1,
+                ok_messages: vec![(1, 1)]
+            },
+            confirmation
+        );
+        assert_eq!(true, logger.has_message("are dropped"));
+        assert_eq!(0, client.get_published().len());
+    }
+
+    #[tokio::test]
+    async fn test_reject_strategy_moves_message_to_dead_letter_after_max_attempts() {
+        let client = TestClient::new();
+
+        let (confirmation, handled) = deliver(
+            &client,
+            &TestLogger::new(),
+            SubscriberSettings {
+                dead_letter: Some(create_dead_letter(&client, 100)),
+                poison_message_strategy: PoisonMessageStrategy::Reject { max_attempts: 3 },
+                ..Default::default()
+            },
+            vec![create_message(1, 3, BAD_CONTENT), create_message(2, 0, 2)],
+        )
+        .await;
+
+        assert_eq!(
+            TestConfirmation::All {
+                confirmation_id: 1,
+                delivered: true
+            },
+            confirmation
+        );
+        assert_eq!(vec![vec![2]], handled);
+
+        let published = client.get_published();
+        assert_eq!(1, published.len());
+        assert_eq!("dead-letter", published[0].0);
+        assert_eq!(vec![BAD_CONTENT], published[0].1.content);
+    }
+
+    #[tokio::test]
+    async fn test_quarantine_strategy() {
+        for (messages, expected_handled) in [
+            (
+                vec![create_message(1, 0, 1), create_message(2, 0, BAD_CONTENT)],
+                vec![vec![1]],
+            ),
+            (vec![create_message(2, 0, BAD_CONTENT)], vec![]),
+        ] {
+            let client = TestClient::new();
+            let store = Arc::new(super::super::InMemoryPoisonMessageStore::new());
+
+            let (confirmation, handled) = deliver(
+                &client,
+                &TestLogger::new(),
+                SubscriberSettings {
+                    poison_message_strategy: PoisonMessageStrategy::Quarantine(store.clone()),
+                    ..Default::default()
+                },
+                messages,
+            )
+            .await;
+
+            assert_eq!(
+                TestConfirmation::All {
+                    confirmation_id: 1,
+                    delivered: true
+                },
+                confirmation
+            );
+            assert_eq!(expected_handled, handled);
+
+            let quarantined = store.get_messages();
+            assert_eq!(1, quarantined.len());
+            assert_eq!(2, quarantined[0].message.id.get_value());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dead_letter_strategy() {
+        for (messages, expected_handled) in [
+            (
+                vec![create_message(1, 0, 1), create_message(2, 0, BAD_CONTENT)],
+                vec![vec![1]],
+            ),
+            (vec![create_message(2, 0, BAD_CONTENT)], vec![]),
+        ] {
+            let client = TestClient::new();
+
+            let (confirmation, handled) = deliver(
+                &client,
+                &TestLogger::new(),
+                SubscriberSettings {
+                    poison_message_strategy: PoisonMessageStrategy::DeadLetter(create_dead_letter(
+                        &client, 100,
+                    )),
+                    ..Default::default()
+                },
+                messages,
+            )
+            .await;
+
+            assert_eq!(
+                TestConfirmation::All {
+                    confirmation_id: 1,
+                    delivered: true
+                },
+                confirmation
+            );
+            assert_eq!(expected_handled, handled);
+
+            let published = client.get_published();
+            assert_eq!(1, published.len());
+            assert_eq!(vec![BAD_CONTENT], published[0].1.content);
+        }
+    }
+
+    struct HangingCallback {
+        hanging_id: i64,
+    }
+
+    #[async_trait::async_trait]
+    impl SubscriberCallback<TestMessage> for HangingCallback {
+        async fn handle_messages(
+            &self,
+            messages_reader: &mut MessagesReader<TestMessage>,
+        ) -> Result<(), MySbSubscriberHandleError> {
+            while let Some(msg) = messages_reader.get_next_message() {
+                if msg.id.get_value() == self.hanging_id {
+                    tokio::time::sleep(Duration::from_secs(10)).await;
+                }
+            }
+
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_handler_timeout_confirms_handled_messages() {
+        let client = TestClient::new();
+        let logger = TestLogger::new();
+
+        let subscriber = create_subscriber(
+            &client,
+            &logger,
+            Arc::new(HangingCallback { hanging_id: 2 }),
+            SubscriberSettings {
+                handler_timeout: Some(Duration::from_millis(50)),
+                ..Default::default()
+            },
+        );
+
+        subscriber
+            .new_events(create_messages(&[1, 2, 3]), 1, 0)
+            .await;
+
+        assert_eq!(
+            vec![TestConfirmation::Some {
+                confirmation_id: 1,
+                ok_messages: vec![(1, 1)]
+            }],
+            client.wait_for_confirmations(1).await
+        );
+        assert_eq!(true, logger.has_message("Handler timeout"));
+    }
+
+    struct PanickingCallback;
+
+    #[async_trait::async_trait]
+    impl SubscriberCallback<TestMessage> for PanickingCallback {
+        async fn handle_messages(
+            &self,
+            messages_reader: &mut MessagesReader<TestMessage>,
+        ) -> Result<(), MySbSubscriberHandleError> {
+            while let Some(msg) = messages_reader.get_next_message() {
+                if msg.id.get_value() == 2 {
+                    panic!("Message 2 is broken");
+                }
+            }
+
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_handler_panic_nacks_current_message() {
+        let client = TestClient::new();
+        let logger = TestLogger::new();
+
+        let subscriber = create_subscriber(
+            &client,
+            &logger,
+            Arc::new(PanickingCallback),
+            Default::default(),
+        );
+
+        subscriber
+            .new_events(create_messages(&[1, 2, 3]), 1, 0)
+            .await;
+
+        assert_eq!(
+            vec![TestConfirmation::Some {
+                confirmation_id: 1,
+                ok_messages: vec![(1, 1)]
+            }],
+            client.wait_for_confirmations(1).await
+        );
+        assert_eq!(
+            true,
+            logger.has_message(
+                "Handler panicked. Current message: Some(2). Panic: Message 2 is broken"
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_aborts_handlers_after_timeout() {
+        let client = TestClient::new();
+        let logger = TestLogger::new();
+        let callback = TestCallback::new_with_gate();
+
+        let subscriber = create_subscriber(&client, &logger, callback.clone(), Default::default());
+
+        subscriber.new_events(create_messages(&[1]), 1, 0).await;
+
+        assert_eq!(1, subscriber.shutdown(Duration::from_millis(50)).await);
+
+        assert_eq!(
+            vec![TestConfirmation::All {
+                confirmation_id: 1,
+                delivered: false
+            }],
+            client.wait_for_confirmations(1).await
+        );
+        assert_eq!(true, callback.get_handled().is_empty());
+        assert_eq!(true, logger.has_message("are aborted"));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_deliveries_racing_shutdown_are_confirmed() {
+        for is_paused in [false, true] {
+            let client = TestClient::new();
+
+            let subscriber = create_subscriber(
+                &client,
+                &TestLogger::new(),
+                TestCallback::new(),
+                SubscriberSettings {
+                    pause_policy: SubscriberPausePolicy::Buffer {
+                        max_deliveries: 1000,
+                    },
+                    ..Default::default()
+                },
+            );
+
+            if is_paused {
+                subscriber.pause();
+            }
+
+            let mut tasks = Vec::new();
+
+            for confirmation_id in 1..=100 {
+                let subscriber = subscriber.clone();
+                tasks.push(tokio::spawn(async move {
+                    subscriber
+                        .new_events(create_messages(&[confirmation_id]), confirmation_id, 0)
+                        .await
+                }));
+            }
+
+            assert_eq!(0, subscriber.shutdown(Duration::from_secs(5)).await);
+
+            for task in tasks {
+                task.await.unwrap();
+            }
+
+            let confirmations = client.get_confirmations();
+            assert_eq!(100, confirmations.len());
+            assert_eq!(0, subscriber.get_paused_deliveries_amount());
+
+            if is_paused {
+                for confirmation in confirmations {
+                    assert_eq!(
+                        true,
+                        matches!(
+                            confirmation,
+                            TestConfirmation::All {
+                                delivered: false,
+                                ..
+                            }
+                        )
+                    );
+                }
             }
-        });
+        }
+    }
+
+    #[tokio::test]
+    async fn test_paused_buffer_overflow_is_rejected() {
+        let client = TestClient::new();
+        let logger = TestLogger::new();
+        let callback = TestCallback::new();
+
+        let subscriber = create_subscriber(
+            &client,
+            &logger,
+            callback.clone(),
+            SubscriberSettings {
+                pause_policy: SubscriberPausePolicy::Buffer { max_deliveries: 2 },
+                ..Default::default()
+            },
+        );
+
+        subscriber.pause();
+
+        for confirmation_id in 1..=3 {
+            subscriber
+                .new_events(create_messages(&[confirmation_id]), confirmation_id, 0)
+                .await;
+        }
+
+        assert_eq!(SubscriberState::Paused, subscriber.get_state());
+        assert_eq!(2, subscriber.get_paused_deliveries_amount());
+        assert_eq!(
+            vec![TestConfirmation::All {
+                confirmation_id: 3,
+                delivered: false
+            }],
+            client.get_confirmations()
+        );
+        assert_eq!(true, logger.has_message("Subscriber is paused"));
+
+        subscriber.resume().await;
+
+        assert_eq!(SubscriberState::Active, subscriber.get_state());
+        assert_eq!(0, subscriber.get_paused_deliveries_amount());
+        assert_eq!(3, client.wait_for_confirmations(3).await.len());
+
+        let mut handled = callback.get_handled();
+        handled.sort();
+        assert_eq!(vec![vec![1], vec![2]], handled);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_resume_keeps_order() {
+        let client = TestClient::new();
+        let callback = TestCallback::new();
+
+        let subscriber = create_subscriber(
+            &client,
+            &TestLogger::new(),
+            callback.clone(),
+            SubscriberSettings {
+                processing_mode: SubscriberProcessingMode::Sequential,
+                max_pending_deliveries: 1,
+                pause_policy: SubscriberPausePolicy::Buffer {
+                    max_deliveries: 100,
+                },
+                ..Default::default()
+            },
+        );
+
+        subscriber.pause();
+
+        for confirmation_id in 1..=20 {
+            subscriber
+                .new_events(create_messages(&[confirmation_id]), confirmation_id, 0)
+                .await;
+        }
+
+        tokio::join!(
+            subscriber.resume(),
+            subscriber.resume(),
+            subscriber.new_events(create_messages(&[21]), 21, 0)
+        );
+
+        client.wait_for_confirmations(21).await;
+
+        assert_eq!(SubscriberState::Active, subscriber.get_state());
+        assert_eq!(
+            (1..=21).map(|id| vec![id]).collect::<Vec<_>>(),
+            callback.get_handled()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_pause_during_resume_keeps_deliveries_buffered() {
+        let client = TestClient::new();
+        let callback = TestCallback::new_with_gate();
+
+        let subscriber = create_subscriber(
+            &client,
+            &TestLogger::new(),
+            callback.clone(),
+            SubscriberSettings {
+                processing_mode: SubscriberProcessingMode::Sequential,
+                max_pending_deliveries: 1,
+                pause_policy: SubscriberPausePolicy::Buffer {
+                    max_deliveries: 100,
+                },
+                ..Default::default()
+            },
+        );
+
+        subscriber.pause();
+
+        for confirmation_id in 1..=5 {
+            subscriber
+                .new_events(create_messages(&[confirmation_id]), confirmation_id, 0)
+                .await;
+        }
+
+        let resume = {
+            let subscriber = subscriber.clone();
+            tokio::spawn(async move { subscriber.resume().await })
+        };
+
+        while subscriber.get_paused_deliveries_amount() > 2 {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+
+        subscriber.pause();
+        callback.open_gate(5);
+        resume.await.unwrap();
+
+        assert_eq!(SubscriberState::Paused, subscriber.get_state());
+        assert_eq!(true, subscriber.get_paused_deliveries_amount() > 0);
+    }
+
+    #[tokio::test]
+    async fn test_sequential_mode_applies_backpressure() {
+        let client = TestClient::new();
+        let callback = TestCallback::new_with_gate();
+
+        let subscriber = create_subscriber(
+            &client,
+            &TestLogger::new(),
+            callback.clone(),
+            SubscriberSettings {
+                max_concurrent_handlers: Some(1),
+                processing_mode: SubscriberProcessingMode::Sequential,
+                ..Default::default()
+            },
+        );
+
+        subscriber.new_events(create_messages(&[1]), 1, 0).await;
+
+        let second_delivery = {
+            let subscriber = subscriber.clone();
+            tokio::spawn(async move { subscriber.new_events(create_messages(&[2]), 2, 0).await })
+        };
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(false, second_delivery.is_finished());
+
+        callback.open_gate(2);
+
+        tokio::time::timeout(Duration::from_secs(1), second_delivery)
+            .await
+            .unwrap()
+            .unwrap();
+
+        client.wait_for_confirmations(2).await;
+        assert_eq!(vec![vec![1], vec![2]], callback.get_handled());
+    }
+
+    #[tokio::test]
+    async fn test_sequential_queue_is_bounded_and_keeps_order() {
+        let client = TestClient::new();
+        let callback = TestCallback::new_with_gate();
+
+        let subscriber = create_subscriber(
+            &client,
+            &TestLogger::new(),
+            callback.clone(),
+            SubscriberSettings {
+                processing_mode: SubscriberProcessingMode::Sequential,
+                max_pending_deliveries: 1,
+                ..Default::default()
+            },
+        );
+
+        subscriber.new_events(create_messages(&[1, 2]), 1, 0).await;
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        subscriber.new_events(create_messages(&[3]), 2, 0).await;
+
+        let third_delivery = {
+            let subscriber = subscriber.clone();
+            tokio::spawn(async move { subscriber.new_events(create_messages(&[4]), 3, 0).await })
+        };
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(false, third_delivery.is_finished());
+        assert_eq!(0, client.get_confirmations().len());
+
+        callback.open_gate(3);
+
+        tokio::time::timeout(Duration::from_secs(1), third_delivery)
+            .await
+            .unwrap()
+            .unwrap();
+
+        let confirmations = client.wait_for_confirmations(3).await;
+
+        assert_eq!(vec![vec![1, 2], vec![3], vec![4]], callback.get_handled());
+        assert_eq!(
+            vec![
+                TestConfirmation::All {
+                    confirmation_id: 1,
+                    delivered: true
+                },
+                TestConfirmation::All {
+                    confirmation_id: 2,
+                    delivered: true
+                },
+                TestConfirmation::All {
+                    confirmation_id: 3,
+                    delivered: true
+                },
+            ],
+            confirmations
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sequential_mode_keeps_order_of_poison_only_delivery() {
+        let client = TestClient::new();
+        let callback = TestCallback::new_with_gate();
+
+        let subscriber = create_subscriber(
+            &client,
+            &TestLogger::new(),
+            callback.clone(),
+            SubscriberSettings {
+                processing_mode: SubscriberProcessingMode::Sequential,
+                ..Default::default()
+            },
+        );
+
+        subscriber.new_events(create_messages(&[1]), 1, 0).await;
+        subscriber
+            .new_events(vec![create_message(2, 0, BAD_CONTENT)], 2, 0)
+            .await;
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(0, client.get_confirmations().len());
+
+        callback.open_gate(1);
+
+        assert_eq!(
+            vec![
+                TestConfirmation::All {
+                    confirmation_id: 1,
+                    delivered: true
+                },
+                TestConfirmation::All {
+                    confirmation_id: 2,
+                    delivered: false
+                },
+            ],
+            client.wait_for_confirmations(2).await
+        );
     }
 }
@@ -0,0 +1,262 @@
+use std::{
+    ops::{Deref, DerefMut},
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use futures::Stream;
+use rust_extensions::{Logger, StrOrString};
+use tokio::sync::{mpsc, oneshot};
+
+use crate::MyServiceBusSubscriberClient;
+
+use super::{
+    MessageHandleResult, MessagesReader, MySbDeliveredMessage, MySbMessageDeserializer,
+    MySbSubscriberHandleError, Subscriber, SubscriberCallback, SubscriberSettings, TopicQueueType,
+};
+
+pub struct MySbStreamMessage<TMessageModel: MySbMessageDeserializer<Item = TMessageModel>> {
+    message: MySbDeliveredMessage<TMessageModel>,
+    sender: Option<oneshot::Sender<MessageHandleResult>>,
+}
+
+impl<TMessageModel: MySbMessageDeserializer<Item = TMessageModel>>
+    MySbStreamMessage<TMessageModel>
+{
+    pub fn ack(mut self) {
+        self.resolve(MessageHandleResult::Ack);
+    }
+
+    pub fn nack(mut self) {
+        self.resolve(MessageHandleResult::Nack);
+    }
+
+    fn resolve(&mut self, handle_result: MessageHandleResult) {
+        if let Some(sender) = self.sender.take() {
+            let _ = sender.send(handle_result);
+        }
+    }
+}
+
+impl<TMessageModel: MySbMessageDeserializer<Item = TMessageModel>> Deref
+    for MySbStreamMessage<TMessageModel>
+{
+    type Target = MySbDeliveredMessage<TMessageModel>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.message
+    }
+}
+
+impl<TMessageModel: MySbMessageDeserializer<Item = TMessageModel>> DerefMut
+    for MySbStreamMessage<TMessageModel>
+{
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.message
+    }
+}
+
+impl<TMessageModel: MySbMessageDeserializer<Item = TMessageModel>> Drop
+    for MySbStreamMessage<TMessageModel>
+{
+    fn drop(&mut self) {
+        let handle_result = self
+            .message
+            .handle_result
+            .unwrap_or(MessageHandleResult::Nack);
+
+        self.resolve(handle_result);
+    }
+}
+
+pub struct SubscriberStream<TMessageModel: MySbMessageDeserializer<Item = TMessageModel>> {
+    receiver: mpsc::Receiver<MySbStreamMessage<TMessageModel>>,
+}
+
+impl<TMessageModel: MySbMessageDeserializer<Item = TMessageModel>> Stream
+    for SubscriberStream<TMessageModel>
+{
+    type Item = MySbStreamMessage<TMessageModel>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.receiver.poll_recv(cx)
+    }
+}
+
+struct SubscriberStreamCallback<TMessageModel: MySbMessageDeserializer<Item = TMessageModel>> {
+    sender: mpsc::Sender<MySbStreamMessage<TMessageModel>>,
+}
+
+#[async_trait::async_trait]
+impl<TMessageModel: MySbMessageDeserializer<Item = TMessageModel> + Send + Sync + 'static>
+    SubscriberCallback<TMessageModel> for SubscriberStreamCallback<TMessageModel>
+{
+    async fn handle_messages(
+        &self,
+        messages_reader: &mut MessagesReader<TMessageModel>,
+    ) -> Result<(), MySbSubscriberHandleError> {
+        let messages = match messages_reader.get_all() {
+            Some(messages) => messages,
+            None => return Ok(()),
+        };
+
+        let mut results = Vec::with_capacity(messages.len());
+
+        for message in messages {
+            let id = message.id;
+            let (sender, receiver) = oneshot::channel();
+
+            let stream_message = MySbStreamMessage {
+                message,
+                sender: Some(sender),
+            };
+
+            if self.sender.send(stream_message).await.is_err() {
+                return Err(MySbSubscriberHandleError {
+                    msg: "Subscriber stream is closed".to_string(),
+                });
+            }
+
+            results.push((id, receiver));
+        }
+
+        for (id, receiver) in results {
+            match receiver.await {
+                Ok(MessageHandleResult::Ack) => {
+                    messages_reader.ack(id);
+                }
+                _ => {
+                    messages_reader.nack(id);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<TMessageModel: MySbMessageDeserializer<Item = TMessageModel> + Send + Sync + 'static>
+    Subscriber<TMessageModel>
+{
+    pub fn new_stream(
+        topic_id: StrOrString<'static>,
+        queue_id: StrOrString<'static>,
+        queue_type: TopicQueueType,
+        logger: Arc<dyn Logger + Sync + Send + 'static>,
+        client: Arc<dyn MyServiceBusSubscriberClient + Sync + Send + 'static>,
+        settings: SubscriberSettings,
+        buffer_size: usize,
+    ) -> (Self, SubscriberStream<TMessageModel>) {
+        let (sender, receiver) = mpsc::channel(buffer_size.max(1));
+
+        let subscriber = Self::new_with_settings(
+            topic_id,
+            queue_id,
+            queue_type,
+            Arc::new(SubscriberStreamCallback { sender }),
+            logger,
+            client,
+            settings,
+        );
+
+        (subscriber, SubscriberStream { receiver })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use futures::StreamExt;
+
+    use super::super::test_utils::*;
+    use super::*;
+    use crate::MyServiceBusSubscriberClientCallback;
+
+    fn create_stream(
+        client: &Arc<TestClient>,
+        buffer_size: usize,
+    ) -> (Subscriber<TestMessage>, SubscriberStream<TestMessage>) {
+        Subscriber::new_stream(
+            "test-topic".into(),
+            "test-queue".into(),
+            TopicQueueType::Permanent,
+            TestLogger::new(),
+            client.clone(),
+            Default::default(),
+            buffer_size,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_zero_buffer_size_stream_delivers_messages() {
+        let client = TestClient::new();
+        let (subscriber, mut stream) = create_stream(&client, 0);
+
+        subscriber.new_events(create_messages(&[1, 2]), 1, 0).await;
+
+        let first = stream.next().await.unwrap();
+        assert_eq!(1, first.id.get_value());
+        first.ack();
+
+        let second = stream.next().await.unwrap();
+        assert_eq!(2, second.id.get_value());
+        second.ack();
+
+        assert_eq!(
+            vec![TestConfirmation::All {
+                confirmation_id: 1,
+                delivered: true
+            }],
+            client.wait_for_confirmations(1).await
+        );
+    }
+
+    #[tokio::test]
+    async fn test_dropped_message_is_nacked() {
+        let client = TestClient::new();
+        let (subscriber, mut stream) = create_stream(&client, 10);
+
+        subscriber
+            .new_events(create_messages(&[1, 2, 3]), 1, 0)
+            .await;
+
+        stream.next().await.unwrap().ack();
+        drop(stream.next().await.unwrap());
+        stream.next().await.unwrap().nack();
+
+        assert_eq!(
+            vec![TestConfirmation::Some {
+                confirmation_id: 1,
+                ok_messages: vec![(1, 1)]
+            }],
+            client.wait_for_confirmations(1).await
+        );
+    }
+
+    #[tokio::test]
+    async fn test_delivery_is_confirmed_after_all_messages_are_resolved() {
+        let client = TestClient::new();
+        let (subscriber, mut stream) = create_stream(&client, 10);
+
+        subscriber.new_events(create_messages(&[1, 2]), 1, 0).await;
+
+        let first = stream.next().await.unwrap();
+        let second = stream.next().await.unwrap();
+
+        first.ack();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(0, client.get_confirmations().len());
+
+        second.ack();
+
+        assert_eq!(
+            vec![TestConfirmation::All {
+                confirmation_id: 1,
+                delivered: true
+            }],
+            client.wait_for_confirmations(1).await
+        );
+    }
+}
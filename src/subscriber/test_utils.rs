@@ -0,0 +1,280 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use tokio::sync::Semaphore;
+
+pub use crate::test_utils::TestLogger;
+
+use crate::{
+    publisher::MessageToPublish, queue_with_intervals::QueueIndexRange, MessageId, MySbMessage,
+    MyServiceBusPublisherClient, MyServiceBusSubscriberClient, PublishError, SubscriberError,
+};
+
+use super::{
+    MessagesReader, MySbDeliveredMessage, MySbMessageDeserializer, MySbSubscriberHandleError,
+    Subscriber, SubscriberCallback, SubscriberData, SubscriberSettings, TopicQueueType,
+};
+
+pub const BAD_CONTENT: u8 = 255;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TestConfirmation {
+    All {
+        confirmation_id: i64,
+        delivered: bool,
+    },
+    Some {
+        confirmation_id: i64,
+        ok_messages: Vec<(i64, i64)>,
+    },
+}
+
+pub struct TestClient {
+    confirmations: Mutex<Vec<TestConfirmation>>,
+    published: Mutex<Vec<(String, MessageToPublish)>>,
+}
+
+impl TestClient {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self {
+            confirmations: Mutex::new(Vec::new()),
+            published: Mutex::new(Vec::new()),
+        })
+    }
+
+    pub fn get_confirmations(&self) -> Vec<TestConfirmation> {
+        self.confirmations.lock().unwrap().clone()
+    }
+
+    pub fn get_published(&self) -> Vec<(String, MessageToPublish)> {
+        self.published.lock().unwrap().clone()
+    }
+
+    pub async fn wait_for_confirmations(&self, amount: usize) -> Vec<TestConfirmation> {
+        for _ in 0..1000 {
+            let confirmations = self.get_confirmations();
+
+            if confirmations.len() >= amount {
+                return confirmations;
+            }
+
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+
+        panic!(
+            "Expected {} confirmations. Got: {:?}",
+            amount,
+            self.get_confirmations()
+        );
+    }
+}
+
+impl MyServiceBusSubscriberClient for TestClient {
+    fn confirm_delivery(
+        &self,
+        _topic_id: &str,
+        _queue_id: &str,
+        confirmation_id: i64,
+        _connection_id: i32,
+        delivered: bool,
+    ) {
+        self.confirmations
+            .lock()
+            .unwrap()
+            .push(TestConfirmation::All {
+                confirmation_id,
+                delivered,
+            });
+    }
+
+    fn confirm_some_messages_ok(
+        &self,
+        _topic_id: &str,
+        _queue_id: &str,
+        confirmation_id: i64,
+        _connection_id: i32,
+        ok_messages: Vec<QueueIndexRange>,
+    ) {
+        self.confirmations
+            .lock()
+            .unwrap()
+            .push(TestConfirmation::Some {
+                confirmation_id,
+                ok_messages: ok_messages
+                    .iter()
+                    .map(|itm| (itm.from_id, itm.to_id))
+                    .collect(),
+            });
+    }
+}
+
+#[async_trait::async_trait]
+impl MyServiceBusPublisherClient for TestClient {
+    async fn publish_message(
+        &self,
+        topic_id: &str,
+        message: MessageToPublish,
+        do_retry: bool,
+    ) -> Result<(), PublishError> {
+        self.publish_messages(topic_id, &[message], do_retry).await
+    }
+
+    async fn publish_messages(
+        &self,
+        topic_id: &str,
+        messages: &[MessageToPublish],
+        _do_retry: bool,
+    ) -> Result<(), PublishError> {
+        let mut published = self.published.lock().unwrap();
+
+        for message in messages {
+            published.push((topic_id.to_string(), message.clone()));
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct TestMessage(#[allow(dead_code)] pub u8);
+
+impl MySbMessageDeserializer for TestMessage {
+    type Item = TestMessage;
+
+    fn deserialize(
+        src: &[u8],
+        _headers: &Option<HashMap<String, String>>,
+    ) -> Result<Self::Item, SubscriberError> {
+        if src[0] == BAD_CONTENT {
+            return Err(SubscriberError::CanNotDeserializeMessage(
+                "Bad content".to_string(),
+            ));
+        }
+
+        Ok(TestMessage(src[0]))
+    }
+}
+
+pub fn create_subscriber_data(
+    client: &Arc<TestClient>,
+    logger: &Arc<TestLogger>,
+    settings: SubscriberSettings,
+) -> Arc<SubscriberData> {
+    Arc::new(SubscriberData::new(
+        "test-topic".into(),
+        "test-queue".into(),
+        TopicQueueType::Permanent,
+        logger.clone(),
+        client.clone(),
+        settings,
+    ))
+}
+
+pub fn create_reader(
+    data: &Arc<SubscriberData>,
+    ids: &[i64],
+    confirmation_id: i64,
+) -> MessagesReader<TestMessage> {
+    let messages: VecDeque<MySbDeliveredMessage<TestMessage>> = ids
+        .iter()
+        .map(|id| {
+            MySbDeliveredMessage::new(
+                MessageId::new(*id),
+                0,
+                None,
+                vec![*id as u8],
+                TestMessage(*id as u8),
+            )
+        })
+        .collect();
+
+    MessagesReader::new(data.clone(), messages, confirmation_id, 0)
+}
+
+pub fn create_message(id: i64, attempt_no: i32, content: u8) -> MySbMessage {
+    MySbMessage {
+        id: MessageId::new(id),
+        attempt_no,
+        headers: None,
+        content: vec![content],
+    }
+}
+
+pub fn create_messages(ids: &[i64]) -> Vec<MySbMessage> {
+    ids.iter()
+        .map(|id| create_message(*id, 0, *id as u8))
+        .collect()
+}
+
+pub struct TestCallback {
+    handled: Mutex<Vec<Vec<i64>>>,
+    gate: Option<Semaphore>,
+}
+
+impl TestCallback {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self {
+            handled: Mutex::new(Vec::new()),
+            gate: None,
+        })
+    }
+
+    pub fn new_with_gate() -> Arc<Self> {
+        Arc::new(Self {
+            handled: Mutex::new(Vec::new()),
+            gate: Some(Semaphore::new(0)),
+        })
+    }
+
+    pub fn open_gate(&self, deliveries: usize) {
+        if let Some(gate) = self.gate.as_ref() {
+            gate.add_permits(deliveries);
+        }
+    }
+
+    pub fn get_handled(&self) -> Vec<Vec<i64>> {
+        self.handled.lock().unwrap().clone()
+    }
+}
+
+#[async_trait::async_trait]
+impl SubscriberCallback<TestMessage> for TestCallback {
+    async fn handle_messages(
+        &self,
+        messages_reader: &mut MessagesReader<TestMessage>,
+    ) -> Result<(), MySbSubscriberHandleError> {
+        if let Some(gate) = self.gate.as_ref() {
+            gate.acquire().await.unwrap().forget();
+        }
+
+        let mut ids = Vec::new();
+
+        while let Some(msg) = messages_reader.get_next_message() {
+            ids.push(msg.id.get_value());
+        }
+
+        self.handled.lock().unwrap().push(ids);
+
+        Ok(())
+    }
+}
+
+pub fn create_subscriber(
+    client: &Arc<TestClient>,
+    logger: &Arc<TestLogger>,
+    callback: Arc<dyn SubscriberCallback<TestMessage> + Send + Sync + 'static>,
+    settings: SubscriberSettings,
+) -> Arc<Subscriber<TestMessage>> {
+    Arc::new(Subscriber::new_with_settings(
+        "test-topic".into(),
+        "test-queue".into(),
+        TopicQueueType::Permanent,
+        callback,
+        logger.clone(),
+        client.clone(),
+        settings,
+    ))
+}
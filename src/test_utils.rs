@@ -0,0 +1,218 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use rust_extensions::Logger;
+
+use crate::{
+    publisher::{
+        MessageToPublish, MySbMessageSerializer, MyServiceBusPublishInterceptor, PublishContext,
+    },
+    MyServiceBusPublisherClient, PublishError,
+};
+
+pub struct TestLogger {
+    messages: Mutex<Vec<String>>,
+}
+
+impl TestLogger {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self {
+            messages: Mutex::new(Vec::new()),
+        })
+    }
+
+    pub fn has_message(&self, part: &str) -> bool {
+        self.messages
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|itm| itm.contains(part))
+    }
+
+    fn write(&self, message: String) {
+        self.messages.lock().unwrap().push(message);
+    }
+}
+
+impl Logger for TestLogger {
+    fn write_info(&self, _process: String, message: String, _ctx: Option<HashMap<String, String>>) {
+        self.write(message);
+    }
+
+    fn write_warning(
+        &self,
+        _process: String,
+        message: String,
+        _ctx: Option<HashMap<String, String>>,
+    ) {
+        self.write(message);
+    }
+
+    fn write_error(
+        &self,
+        _process: String,
+        message: String,
+        _ctx: Option<HashMap<String, String>>,
+    ) {
+        self.write(message);
+    }
+
+    fn write_fatal_error(
+        &self,
+        _process: String,
+        message: String,
+        _ctx: Option<HashMap<String, String>>,
+    ) {
+        self.write(message);
+    }
+
+    fn write_debug_info(
+        &self,
+        _process: String,
+        message: String,
+        _ctx: Option<HashMap<String, String>>,
+    ) {
+        self.write(message);
+    }
+}
+
+pub struct TestPublisherClient {
+    batches: Mutex<Vec<Vec<MessageToPublish>>>,
+    attempts_to_fail: AtomicUsize,
+    attempts: AtomicUsize,
+    delay: Duration,
+}
+
+impl TestPublisherClient {
+    pub fn new() -> Arc<Self> {
+        Self::new_with_delay(Duration::from_millis(0))
+    }
+
+    pub fn new_with_delay(delay: Duration) -> Arc<Self> {
+        Arc::new(Self {
+            batches: Mutex::new(Vec::new()),
+            attempts_to_fail: AtomicUsize::new(0),
+            attempts: AtomicUsize::new(0),
+            delay,
+        })
+    }
+
+    pub fn get_attempts(&self) -> usize {
+        self.attempts.load(Ordering::SeqCst)
+    }
+
+    pub fn fail_next_attempts(&self, amount: usize) {
+        self.attempts_to_fail.store(amount, Ordering::SeqCst);
+    }
+
+    pub fn get_published_contents_raw(&self) -> Vec<Vec<u8>> {
+        self.batches
+            .lock()
+            .unwrap()
+            .iter()
+            .flatten()
+            .map(|itm| itm.content.clone())
+            .collect()
+    }
+
+    pub fn get_published_contents(&self) -> Vec<u8> {
+        self.batches
+            .lock()
+            .unwrap()
+            .iter()
+            .flatten()
+            .map(|itm| itm.content[0])
+            .collect()
+    }
+}
+
+#[async_trait::async_trait]
+impl MyServiceBusPublisherClient for TestPublisherClient {
+    async fn publish_message(
+        &self,
+        topic_id: &str,
+        message: MessageToPublish,
+        do_retry: bool,
+    ) -> Result<(), PublishError> {
+        self.publish_messages(topic_id, &[message], do_retry).await
+    }
+
+    async fn publish_messages(
+        &self,
+        _topic_id: &str,
+        messages: &[MessageToPublish],
+        _do_retry: bool,
+    ) -> Result<(), PublishError> {
+        self.attempts.fetch_add(1, Ordering::SeqCst);
+        tokio::time::sleep(self.delay).await;
+
+        let should_fail = self
+            .attempts_to_fail
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |value| {
+                value.checked_sub(1)
+            })
+            .is_ok();
+
+        if should_fail {
+            return Err(PublishError::NoConnectionToPublish);
+        }
+
+        self.batches.lock().unwrap().push(messages.to_vec());
+        Ok(())
+    }
+}
+
+pub struct TestPublishMessage(pub u8);
+
+impl MySbMessageSerializer for TestPublishMessage {
+    fn serialize(
+        &self,
+        headers: Option<HashMap<String, String>>,
+    ) -> Result<(Vec<u8>, Option<HashMap<String, String>>), String> {
+        Ok((vec![self.0], headers))
+    }
+}
+
+pub struct TestInterceptor {
+    pub name: &'static str,
+    pub calls: Arc<Mutex<Vec<String>>>,
+    pub reject: bool,
+}
+
+impl MyServiceBusPublishInterceptor for TestInterceptor {
+    fn before_publish(
+        &self,
+        _ctx: &PublishContext,
+        message: &mut MessageToPublish,
+    ) -> Result<(), PublishError> {
+        self.calls
+            .lock()
+            .unwrap()
+            .push(format!("{}:before", self.name));
+
+        if self.reject {
+            return Err(PublishError::Other(format!("Rejected by {}", self.name)));
+        }
+
+        message.content.push(self.name.as_bytes()[0]);
+        Ok(())
+    }
+
+    fn after_publish(
+        &self,
+        _topic_id: &str,
+        messages: &[MessageToPublish],
+        _result: &Result<(), PublishError>,
+    ) {
+        self.calls
+            .lock()
+            .unwrap()
+            .push(format!("{}:after:{}", self.name, messages.len()));
+    }
+}